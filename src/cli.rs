@@ -1,45 +1,421 @@
 mod opt;
 
+use std::collections::BTreeMap;
 use std::fs::{read_to_string, File};
 use std::io::{self, Read, Write};
+use std::path::Path;
 
+use anyhow::{bail, ensure, Context};
 use clap::StructOpt;
 
-use crate::generator::{generate_excel, generate_markdown};
-use crate::testspec::TestSpec;
+use crate::config::{Config, Settings};
+use crate::generator::{
+    generate_delimited, generate_docx, generate_excel, generate_gherkin, generate_html,
+    generate_junit, generate_markdown, generate_ods, generate_pdf, generate_template,
+};
+use crate::importer::{import_excel, import_gherkin, import_markdown, Imported};
+use crate::lint::{lint, report_json, report_sarif, report_text, Diagnostic, LintConfig, Severity};
+use crate::testspec::{Results, SpecFormat, TestSpec};
 
-use self::opt::{Format, Input, Opt, Output};
+use self::opt::{
+    AssignIdsOpt, Command, ConfigOpt, FmtOpt, Format, GenerateOpt, ImportOpt, InitOpt, Input,
+    LintFormat, LintOpt, Opt, Output, StatsOpt, ValidateOpt,
+};
+
+const STARTER_SPEC: &str = include_str!("../templates/testspec.yml");
 
 pub fn execute() -> anyhow::Result<()> {
     let opt = Opt::parse();
 
-    let input = match &opt.input {
-        Input::StdIn => {
-            let mut buf = String::new();
-            io::stdin().lock().read_to_string(&mut buf)?;
-            buf
-        }
-        Input::Path(s) => read_to_string(&s)?,
+    match &opt.command {
+        Some(Command::Generate(o)) => generate(o),
+        Some(Command::Validate(o)) => validate(o),
+        Some(Command::Fmt(o)) => fmt(o),
+        Some(Command::Lint(o)) => lint_spec(o),
+        Some(Command::Stats(o)) => stats(o),
+        Some(Command::Init(o)) => init(o),
+        Some(Command::Schema(o)) => write_output(
+            o.output.as_ref().unwrap_or(&Output::StdOut),
+            (serde_json::to_string_pretty(&TestSpec::schema())? + "\n").as_bytes(),
+        ),
+        Some(Command::AssignIds(o)) => assign_ids(o),
+        Some(Command::Import(o)) => import(o),
+        None => generate(&opt.generate),
+    }
+}
+
+fn generate(opt: &GenerateOpt) -> anyhow::Result<()> {
+    let (input, output) = match (&opt.input, &opt.output) {
+        (Some(input), Some(output)) => (input, output),
+        _ => unreachable!(),
     };
 
-    let spec: TestSpec = input.parse()?;
+    let mut spec = load(input, opt.input_format)?;
+    let path = match input {
+        Input::StdIn => None,
+        Input::Path(path) => Some(&path[..]),
+    };
+    // Gherkin writes parameterized cases as scenario outlines.
+    if !opt.no_expand && !matches!(opt.format, Format::Gherkin) {
+        spec.expand_parameters()?;
+    }
+    if let Some(path) = &opt.results {
+        let s = read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+        let format = SpecFormat::from_path(path).unwrap_or_else(|| SpecFormat::detect(&s));
+        let results = Results::parse_as(&s, format, Some(path))?;
+        spec.apply_results(&results)?;
+    }
+    spec.resolve_inheritance();
+    if let Some(filter) = &opt.filter {
+        spec.filter(filter);
+    }
 
-    let generate_option = opt.as_generate_option();
+    if let (Some(config), Some(dir)) = (&mut spec.config, path.and_then(|p| Path::new(p).parent()))
+    {
+        config.resolve_paths(dir);
+    }
+    let settings = settings(&opt.config, opt.settings.clone(), spec.config.as_ref())?;
+    let locale = settings.locale()?;
+    let generate_option = settings.as_generate_option(&locale);
     let generated = match opt.format {
-        Format::Markdown => generate_markdown(&spec)?.into_bytes(),
+        Format::Markdown => generate_markdown(&spec, &generate_option)?.into_bytes(),
         Format::Excel => generate_excel(&spec, &generate_option)?,
+        Format::Html => generate_html(&spec, &generate_option)?.into_bytes(),
+        Format::Csv => generate_delimited(&spec, &generate_option, b',')?,
+        Format::Tsv => generate_delimited(&spec, &generate_option, b'\t')?,
+        Format::Junit => generate_junit(&spec)?.into_bytes(),
+        Format::Gherkin => generate_gherkin(&spec)?.into_bytes(),
+        Format::Ods => generate_ods(&spec, &generate_option)?,
+        Format::Docx => generate_docx(&spec, &generate_option)?,
+        Format::Pdf => generate_pdf(&spec, &generate_option)?,
+        Format::Template => match &opt.template {
+            Some(path) => {
+                let template =
+                    read_to_string(path).with_context(|| format!("failed to read {path}"))?;
+                generate_template(&spec, &generate_option, path, &template)?.into_bytes()
+            }
+            None => generate_markdown(&spec, &generate_option)?.into_bytes(),
+        },
+    };
+
+    write_output(output, generated.as_ref())
+}
+
+fn validate(opt: &ValidateOpt) -> anyhow::Result<()> {
+    let mut spec = load(&opt.input, opt.input_format)?;
+    spec.expand_parameters()
+}
+
+fn fmt(opt: &FmtOpt) -> anyhow::Result<()> {
+    let input = read_input(&opt.input)?;
+    let format = spec_format(&opt.input, opt.input_format, &input);
+    let spec = TestSpec::parse_as(&input, format, Some(input_name(&opt.input)))?;
+
+    let formatted = spec.to_string_keeping_comments(format, &input)?;
+    if opt.check {
+        if formatted != input {
+            bail!("{} is not formatted", input_name(&opt.input));
+        }
+        return Ok(());
+    }
+    write_output(
+        &output_or_input(&opt.output, &opt.input),
+        formatted.as_bytes(),
+    )
+}
+
+fn lint_spec(opt: &LintOpt) -> anyhow::Result<()> {
+    let s = read_input(&opt.input)?;
+    let diagnostics = lint_input(opt, s)?;
+
+    let report = match opt.format {
+        LintFormat::Text => report_text(&diagnostics),
+        LintFormat::Json => report_json(&diagnostics)?,
+        LintFormat::Sarif => report_sarif(&diagnostics)?,
+    };
+    write_output(
+        opt.output.as_ref().unwrap_or(&Output::StdOut),
+        report.as_bytes(),
+    )?;
+
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    ensure!(errors == 0, "found {errors} lint error(s)");
+    Ok(())
+}
+
+/// Lints `s`, the content of INPUT.
+fn lint_input(opt: &LintOpt, s: String) -> anyhow::Result<Vec<Diagnostic>> {
+    let path = match &opt.input {
+        Input::StdIn => None,
+        Input::Path(path) => Some(&path[..]),
+    };
+    let spec = TestSpec::load(&s, spec_format(&opt.input, opt.input_format, &s), path)?;
+
+    // Read every file the items came from for its suppression comments.
+    let mut files = BTreeMap::new();
+    files.insert(input_name(&opt.input).to_string(), s);
+    for item in spec.items() {
+        if let Some(source) = &item.source {
+            if !files.contains_key(&source.file) {
+                if let Ok(s) = read_to_string(&source.file) {
+                    files.insert(source.file.clone(), s);
+                }
+            }
+        }
+    }
+
+    let file = config_file(opt.config.as_deref())?;
+    let config = [spec.config.as_ref(), file.as_ref()]
+        .into_iter()
+        .flatten()
+        .filter_map(|c| c.lint.clone())
+        .fold(LintConfig::default(), LintConfig::or);
+    Ok(lint(&spec, &config, &files))
+}
+
+fn stats(opt: &StatsOpt) -> anyhow::Result<()> {
+    let mut spec = load(&opt.input, opt.input_format)?;
+    if !opt.no_expand {
+        spec.expand_parameters()?;
+    }
+
+    let counts = spec.items_per_level();
+    let mut buf = String::new();
+    for (i, count) in counts.iter().enumerate() {
+        buf.push_str(&format!("Level {}: {count}", i + 1));
+        if i + 1 == counts.len() {
+            buf.push_str(" (test cases)");
+        }
+        buf.push('\n');
+    }
+    write_output(&Output::StdOut, buf.as_bytes())
+}
+
+fn init(opt: &InitOpt) -> anyhow::Result<()> {
+    if let Output::Path(path) = &opt.output {
+        ensure!(
+            opt.force || !Path::new(path).exists(),
+            "{path} already exists; use --force to overwrite it"
+        );
+    }
+    write_output(&opt.output, STARTER_SPEC.as_bytes())
+}
+
+fn assign_ids(opt: &AssignIdsOpt) -> anyhow::Result<()> {
+    let input = read_input(&opt.input)?;
+    let format = spec_format(&opt.input, opt.input_format, &input);
+    let path = match &opt.input {
+        Input::StdIn => None,
+        Input::Path(path) => Some(&path[..]),
     };
+    let (spec, fragments) = TestSpec::assign_ids_in_files(&input, format, path, &opt.id_scheme)?;
 
-    match &opt.output {
+    write_output(&output_or_input(&opt.output, &opt.input), spec.as_bytes())?;
+    for (file, s) in fragments {
+        write_output(&Output::Path(file), s.as_bytes())?;
+    }
+    Ok(())
+}
+
+fn import(opt: &ImportOpt) -> anyhow::Result<()> {
+    let bytes =
+        std::fs::read(&opt.input).with_context(|| format!("failed to read {}", opt.input))?;
+    let Imported { mut spec, warnings } =
+        if opt.input.ends_with(".md") || opt.input.ends_with(".markdown") {
+            import_markdown(&String::from_utf8(bytes)?)?
+        } else if opt.input.ends_with(".feature") {
+            let title = Path::new(&opt.input)
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            Imported {
+                spec: import_gherkin(&String::from_utf8(bytes)?, &title)?,
+                warnings: Vec::new(),
+            }
+        } else {
+            let given = Settings {
+                columns: opt.columns.clone(),
+                locale: opt.locale.clone(),
+                ..Default::default()
+            };
+            let settings = settings(&opt.config, given, None)?;
+            let locale = settings.locale()?;
+            import_excel(&bytes, &settings.columns.as_columns_option(&locale))?
+        };
+    for warning in warnings {
+        eprintln!("{}: warning: {warning}", opt.input);
+    }
+
+    if let Some(output) = &opt.results {
+        let results = Results(
+            spec.test_cases_mut()
+                .into_iter()
+                .filter_map(|c| Some((c.id.clone()?, c.result.take()?)))
+                .collect(),
+        );
+        let format = match output {
+            Output::Path(path) => SpecFormat::from_path(path),
+            Output::StdOut => None,
+        };
+        let s = results.to_string_as(format.unwrap_or(SpecFormat::Yaml))?;
+        write_output(output, s.as_bytes())?;
+    }
+
+    let output = opt.output.as_ref().unwrap_or(&Output::StdOut);
+    write_output(output, serde_yaml::to_string(&spec)?.as_bytes())
+}
+
+/// Layers the settings given as options or environment variables over those of the spec's
+/// `config` section, and those over the config file. Within each config, the selected profile
+/// overrides the defaults.
+fn settings(
+    opt: &ConfigOpt,
+    given: Settings,
+    spec_config: Option<&Config>,
+) -> anyhow::Result<Settings> {
+    let file = config_file(opt.config.as_deref())?;
+
+    let profile = opt.profile.as_deref();
+    let mut settings = given;
+    let mut found = false;
+    for config in [spec_config, file.as_ref()].into_iter().flatten() {
+        match config.settings(profile) {
+            Some(s) => {
+                settings = settings.or(s);
+                found = true;
+            }
+            None => settings = settings.or(config.settings.clone()),
+        }
+    }
+    if let Some(profile) = profile {
+        ensure!(found, "profile `{profile}` is not defined");
+    }
+    Ok(settings)
+}
+
+/// Reads the config file given, else the nearest `testspecgen.toml` if there is one.
+fn config_file(path: Option<&str>) -> anyhow::Result<Option<Config>> {
+    let config = match path {
+        Some(path) => Some(Config::load(Path::new(path))?),
+        None => match Config::discover(&std::env::current_dir()?) {
+            Some(path) => Some(Config::load(&path)?),
+            None => None,
+        },
+    };
+    Ok(config)
+}
+
+/// Reads a spec with its includes.
+fn load(input: &Input, format: Option<SpecFormat>) -> anyhow::Result<TestSpec> {
+    let s = read_input(input)?;
+    let path = match input {
+        Input::StdIn => None,
+        Input::Path(path) => Some(&path[..]),
+    };
+    TestSpec::load(&s, spec_format(input, format, &s), path)
+}
+
+/// The given output, else INPUT itself so that it is rewritten in place.
+fn output_or_input(output: &Option<Output>, input: &Input) -> Output {
+    match (output, input) {
+        (Some(Output::StdOut), _) | (None, Input::StdIn) => Output::StdOut,
+        (Some(Output::Path(s)), _) | (None, Input::Path(s)) => Output::Path(s.clone()),
+    }
+}
+
+fn input_name(input: &Input) -> &str {
+    match input {
+        Input::StdIn => "<stdin>",
+        Input::Path(path) => path,
+    }
+}
+
+/// The format given on the command line, else the one of the file extension, else YAML. Specs
+/// read from stdin are detected by their content.
+fn spec_format(input: &Input, format: Option<SpecFormat>, s: &str) -> SpecFormat {
+    match (format, input) {
+        (Some(format), _) => format,
+        (None, Input::Path(path)) => SpecFormat::from_path(path).unwrap_or(SpecFormat::Yaml),
+        (None, Input::StdIn) => SpecFormat::detect(s),
+    }
+}
+
+fn read_input(input: &Input) -> anyhow::Result<String> {
+    let buf = match input {
+        Input::StdIn => {
+            let mut buf = String::new();
+            io::stdin().lock().read_to_string(&mut buf)?;
+            buf
+        }
+        Input::Path(s) => read_to_string(s).with_context(|| format!("failed to read {s}"))?,
+    };
+    Ok(buf)
+}
+
+fn write_output(output: &Output, bytes: &[u8]) -> anyhow::Result<()> {
+    match output {
         Output::StdOut => {
             let mut out = io::stdout();
-            out.write_all(generated.as_ref())?;
+            out.write_all(bytes)?;
         }
         Output::Path(s) => {
-            let mut f = File::create(&s)?;
-            f.write_all(generated.as_ref())?;
+            let mut f = File::create(s)?;
+            f.write_all(bytes)?;
         }
     };
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::lint::Rule;
+
+    #[test]
+    fn starter_spec() {
+        let spec: TestSpec = STARTER_SPEC.parse().unwrap();
+        assert_eq!(2, spec.test_cases().len());
+        let formatted = spec
+            .to_string_keeping_comments(SpecFormat::Yaml, STARTER_SPEC)
+            .unwrap();
+        assert!(formatted.starts_with("# yaml-language-server:"));
+        let spec: TestSpec = formatted.parse().unwrap();
+        assert_eq!(
+            formatted,
+            spec.to_string_keeping_comments(SpecFormat::Yaml, &formatted)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn lint_stdin() {
+        let opt = LintOpt {
+            input: Input::StdIn,
+            output: None,
+            format: LintFormat::Text,
+            input_format: None,
+            config: None,
+        };
+        let s = "title: Spec title
+depth: 1
+cases:
+  - title: Case # testspecgen:disable missing-confirmations
+    operations:
+      - Open the page
+  - title: Case
+    operations:
+      - Open the page
+    confirmations:
+      - Shown
+";
+        let diagnostics = lint_input(&opt, s.to_string()).unwrap();
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(Rule::DuplicateTitle, diagnostics[0].rule);
+        assert_eq!(Some("<stdin>"), diagnostics[0].file.as_deref());
+    }
+}
@@ -1,10 +1,10 @@
 use std::str::FromStr;
 
-use anyhow::{bail, ensure, Error};
-use clap::Parser;
-use regex::Regex;
+use anyhow::{bail, Error};
+use clap::{Args, Parser, Subcommand};
 
-use crate::generator::{ColumnOption, ColumnsOption, GenerateOption};
+use crate::config::{ColumnSettings, Settings};
+use crate::testspec::{Filter, IdScheme, SpecFormat};
 
 #[derive(Debug)]
 pub enum Input {
@@ -12,7 +12,7 @@ pub enum Input {
     Path(String),
 }
 
-impl<'a> FromStr for Input {
+impl FromStr for Input {
     type Err = Error;
     fn from_str(s: &str) -> Result<Input, Self::Err> {
         match s.to_lowercase().as_str() {
@@ -42,6 +42,15 @@ impl FromStr for Output {
 pub enum Format {
     Markdown,
     Excel,
+    Html,
+    Csv,
+    Tsv,
+    Junit,
+    Gherkin,
+    Ods,
+    Docx,
+    Pdf,
+    Template,
 }
 
 impl FromStr for Format {
@@ -50,43 +59,58 @@ impl FromStr for Format {
         match s.to_lowercase().as_str() {
             "markdown" => Ok(Format::Markdown),
             "excel" => Ok(Format::Excel),
+            "html" => Ok(Format::Html),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            "junit" => Ok(Format::Junit),
+            "gherkin" => Ok(Format::Gherkin),
+            "ods" => Ok(Format::Ods),
+            "docx" => Ok(Format::Docx),
+            "pdf" => Ok(Format::Pdf),
+            "template" => Ok(Format::Template),
             _ => bail!("invalid output format: {s}"),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct Color(u32);
-
-impl Color {
-    pub fn into_inner(&self) -> u32 {
-        self.0
-    }
+#[derive(Debug)]
+pub enum LintFormat {
+    Text,
+    Json,
+    Sarif,
 }
 
-impl FromStr for Color {
+impl FromStr for LintFormat {
     type Err = Error;
-    fn from_str(s: &str) -> Result<Color, Self::Err> {
-        let regex = match Regex::new("^0x[0-9a-fA-F]{6}$") {
-            Ok(r) => r,
-            Err(_) => unreachable!(),
-        };
-        ensure!(regex.is_match(s), "invalid color: {s}");
-        let c = match u32::from_str_radix(&s[2..], 16) {
-            Ok(c) => c,
-            Err(_) => unreachable!(),
-        };
-        Ok(Color(c))
+    fn from_str(s: &str) -> Result<LintFormat, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LintFormat::Text),
+            "json" => Ok(LintFormat::Json),
+            "sarif" => Ok(LintFormat::Sarif),
+            _ => bail!("invalid lint output format: {s}"),
+        }
     }
 }
 
 #[derive(Debug, Parser)]
+#[clap(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Opt {
-    #[clap(name = "INPUT")]
-    pub input: Input,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
 
-    #[clap(name = "OUTPUT")]
-    pub output: Output,
+    #[clap(flatten)]
+    pub generate: GenerateOpt,
+}
+
+/// The options of `generate`, which are also accepted without the subcommand.
+#[derive(Debug, Args)]
+#[clap(about = None, long_about = None)]
+pub struct GenerateOpt {
+    #[clap(name = "INPUT", required = true)]
+    pub input: Option<Input>,
+
+    #[clap(name = "OUTPUT", required = true)]
+    pub output: Option<Output>,
 
     #[clap(
         name = "FORMAT",
@@ -97,231 +121,198 @@ pub struct Opt {
     )]
     pub format: Format,
 
-    #[clap(name = "NO_HEADER", long = "no-header", default_value = "No.", env)]
-    pub no_header: String,
+    /// `yaml`, `json` or `toml`; defaults to the INPUT extension, or to the content for stdin
+    #[clap(name = "INPUT_FORMAT", long = "input-format", env)]
+    pub input_format: Option<SpecFormat>,
 
-    #[clap(name = "NO_WIDTH", long = "no-width", default_value = "8", env)]
-    pub no_width: f64,
+    /// Keep parameterized cases as one case listing its parameters instead of one per
+    /// combination (always the case for gherkin)
+    #[clap(long = "no-expand")]
+    pub no_expand: bool,
 
-    #[clap(
-        name = "PRIMARY_ITEM_HEADER",
-        long = "primary-item-header",
-        default_value = "Primary Item",
-        env
-    )]
-    pub primary_item_header: String,
+    /// Results file mapping test case IDs to execution results
+    #[clap(name = "RESULTS", long = "results", env)]
+    pub results: Option<String>,
 
-    #[clap(
-        name = "PRIMARY_ITEM_WIDTH",
-        long = "primary-item-width",
-        default_value = "16",
-        env
-    )]
-    pub primary_item_width: f64,
+    /// Only generate the cases matching an expression such as `tag:smoke and priority<=2`
+    #[clap(name = "FILTER", long = "filter", env)]
+    pub filter: Option<Filter>,
 
-    #[clap(
-        name = "SECONDARY_ITEM_HEADER",
-        long = "secondary-item-header",
-        default_value = "Secondary Item",
-        env
-    )]
-    pub secondary_item_header: String,
+    #[clap(flatten)]
+    pub config: ConfigOpt,
 
-    #[clap(
-        name = "SECONDARY_ITEM_WIDTH",
-        long = "secondary-item-width",
-        default_value = "16",
-        env
-    )]
-    pub secondary_item_width: f64,
+    #[clap(flatten)]
+    pub settings: Settings,
 
-    #[clap(
-        name = "TERTIARY_ITEM_HEADER",
-        long = "tertiary-item-header",
-        default_value = "Tertiary Item",
-        env
-    )]
-    pub tertiary_item_header: String,
+    /// Tera template rendered by `--format template`; defaults to the built-in Markdown
+    /// template
+    #[clap(name = "TEMPLATE", long = "template", env)]
+    pub template: Option<String>,
+}
 
-    #[clap(
-        name = "TERTIARY_ITEM_WIDTH",
-        long = "tertiary-item-width",
-        default_value = "16",
-        env
-    )]
-    pub tertiary_item_width: f64,
+/// Where to read the config file from, and which of its profiles to use.
+#[derive(Debug, Args)]
+#[clap(about = None, long_about = None)]
+pub struct ConfigOpt {
+    /// Config file; defaults to the nearest `testspecgen.toml` in the current directory or
+    /// above
+    #[clap(name = "CONFIG", long = "config", env)]
+    pub config: Option<String>,
+
+    /// Profile of the config file to apply over its defaults
+    #[clap(name = "PROFILE", long = "profile", env)]
+    pub profile: Option<String>,
+}
 
-    #[clap(
-        name = "OPERATOR_HEADER",
-        long = "operator-header",
-        default_value = "Operator",
-        env
-    )]
-    pub operator_header: String,
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Generate a document from a spec; the default when no subcommand is given
+    Generate(Box<GenerateOpt>),
+    /// Check a spec for errors without generating anything
+    Validate(ValidateOpt),
+    /// Rewrite a spec in canonical form
+    Fmt(FmtOpt),
+    /// Check a spec for common mistakes
+    Lint(LintOpt),
+    /// Count the items at each level of a spec
+    Stats(StatsOpt),
+    /// Write a starter spec
+    Init(InitOpt),
+    /// Print the JSON Schema of specs
+    Schema(SchemaOpt),
+    /// Assign IDs to items without one and write the spec back as YAML
+    AssignIds(AssignIdsOpt),
+    /// Read a generated Excel workbook or Markdown file back into a YAML spec and its results
+    Import(Box<ImportOpt>),
+}
 
-    #[clap(
-        name = "OPERATOR_WIDTH",
-        long = "operator-width",
-        default_value = "12",
-        env
-    )]
-    pub operator_width: f64,
+#[derive(Debug, Args)]
+pub struct ValidateOpt {
+    #[clap(name = "INPUT")]
+    pub input: Input,
 
-    #[clap(
-        name = "RESULT_HEADER",
-        long = "result-header",
-        default_value = "Result",
-        env
-    )]
-    pub result_header: String,
+    /// `yaml`, `json` or `toml`; defaults to the INPUT extension, or to the content for stdin
+    #[clap(name = "INPUT_FORMAT", long = "input-format", env)]
+    pub input_format: Option<SpecFormat>,
+}
 
-    #[clap(name = "RESULT_WIDTH", long = "result-width", default_value = "8", env)]
-    pub result_width: f64,
+#[derive(Debug, Args)]
+pub struct FmtOpt {
+    #[clap(name = "INPUT")]
+    pub input: Input,
 
-    #[clap(
-        name = "OPERATIONS_HEADER",
-        long = "operations-header",
-        default_value = "Operations",
-        env
-    )]
-    pub operations_header: String,
+    /// Defaults to overwriting INPUT
+    #[clap(name = "OUTPUT")]
+    pub output: Option<Output>,
 
-    #[clap(
-        name = "OPERATIONS_ITEM_WIDTH",
-        long = "operations-item-width",
-        default_value = "60",
-        env
-    )]
-    pub operations_width: f64,
+    /// Fail instead of writing if INPUT is not in canonical form
+    #[clap(long = "check")]
+    pub check: bool,
 
-    #[clap(
-        name = "CONFIRMATIONS_HEADER",
-        long = "confirmations-header",
-        default_value = "Confirmations",
-        env
-    )]
-    pub confirmations_header: String,
+    /// `yaml`, `json` or `toml`; defaults to the INPUT extension, or to the content for stdin
+    #[clap(name = "INPUT_FORMAT", long = "input-format", env)]
+    pub input_format: Option<SpecFormat>,
+}
 
-    #[clap(
-        name = "CONFIRMATIONS_ITEM_WIDTH",
-        long = "confirmations-item-width",
-        default_value = "60",
-        env
-    )]
-    pub confirmations_width: f64,
+#[derive(Debug, Args)]
+pub struct LintOpt {
+    #[clap(name = "INPUT")]
+    pub input: Input,
 
-    #[clap(
-        name = "REMARKS_HEADER",
-        long = "remarks-header",
-        default_value = "Remarks",
-        env
-    )]
-    pub remarks_header: String,
+    /// Defaults to stdout
+    #[clap(name = "OUTPUT")]
+    pub output: Option<Output>,
 
-    #[clap(
-        name = "REMARKS_ITEM_WIDTH",
-        long = "remarks-item-width",
-        default_value = "60",
-        env
-    )]
-    pub remarks_width: f64,
+    /// `text`, `json` or `sarif`
+    #[clap(name = "LINT_FORMAT", long = "format", default_value = "text", env)]
+    pub format: LintFormat,
 
-    #[clap(
-        name = "FONT_FAMILY",
-        long = "font-family",
-        default_value = "Yu Gothic",
-        env
-    )]
-    pub font_family: String,
+    /// `yaml`, `json` or `toml`; defaults to the INPUT extension, or to the content for stdin
+    #[clap(name = "INPUT_FORMAT", long = "input-format", env)]
+    pub input_format: Option<SpecFormat>,
 
-    #[clap(
-        name = "HEADER_FONT_COLOR",
-        long = "header-font-color",
-        default_value = "0xffffff",
-        env
-    )]
-    pub header_font_color: Color,
+    /// Config file whose `lint` table selects the rules; defaults to the nearest
+    /// `testspecgen.toml` in the current directory or above
+    #[clap(name = "CONFIG", long = "config", env)]
+    pub config: Option<String>,
+}
 
-    #[clap(
-        name = "HEADER_BG_COLOR",
-        long = "header-bg-color",
-        default_value = "0x5b9bd5",
-        env
-    )]
-    pub header_bg_color: Color,
+#[derive(Debug, Args)]
+pub struct StatsOpt {
+    #[clap(name = "INPUT")]
+    pub input: Input,
 
-    #[clap(
-        name = "BODY_FONT_COLOR",
-        long = "body-font-color",
-        default_value = "0x000000",
-        env
-    )]
-    pub body_font_color: Color,
+    /// `yaml`, `json` or `toml`; defaults to the INPUT extension, or to the content for stdin
+    #[clap(name = "INPUT_FORMAT", long = "input-format", env)]
+    pub input_format: Option<SpecFormat>,
 
-    #[clap(
-        name = "BODY_BG_COLOR",
-        long = "body-bg-color",
-        default_value = "0xffffff",
-        env
-    )]
-    pub body_bg_color: Color,
+    /// Count parameterized cases once instead of once per combination
+    #[clap(long = "no-expand")]
+    pub no_expand: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct InitOpt {
+    #[clap(name = "OUTPUT", default_value = "testspec.yml")]
+    pub output: Output,
 
+    /// Overwrite OUTPUT if it exists
+    #[clap(long = "force")]
+    pub force: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct SchemaOpt {
+    /// Defaults to stdout
+    #[clap(name = "OUTPUT")]
+    pub output: Option<Output>,
+}
+
+#[derive(Debug, Args)]
+pub struct AssignIdsOpt {
+    #[clap(name = "INPUT")]
+    pub input: Input,
+
+    /// Defaults to overwriting INPUT; files it includes are always rewritten in place
+    #[clap(name = "OUTPUT")]
+    pub output: Option<Output>,
+
+    /// `hierarchical` (1.2.3) or a pattern such as `LOGIN-###`
     #[clap(
-        name = "BORDER_COLOR",
-        long = "border-color",
-        default_value = "0x5b9bd5",
+        name = "ID_SCHEME",
+        long = "id-scheme",
+        default_value = "hierarchical",
         env
     )]
-    pub border_color: Color,
+    pub id_scheme: IdScheme,
+
+    /// `yaml`, `json` or `toml`; defaults to the INPUT extension, or to the content for stdin
+    #[clap(name = "INPUT_FORMAT", long = "input-format", env)]
+    pub input_format: Option<SpecFormat>,
 }
 
-impl Opt {
-    pub fn as_generate_option(&self) -> GenerateOption<'_> {
-        GenerateOption {
-            column_options: ColumnsOption {
-                no_column: ColumnOption {
-                    header: &self.no_header,
-                    width: self.no_width,
-                },
-                primary_item_column: ColumnOption {
-                    header: &self.primary_item_header,
-                    width: self.primary_item_width,
-                },
-                secondary_item_column: ColumnOption {
-                    header: &self.secondary_item_header,
-                    width: self.secondary_item_width,
-                },
-                tertiary_item_column: ColumnOption {
-                    header: &self.tertiary_item_header,
-                    width: self.tertiary_item_width,
-                },
-                operator_column: ColumnOption {
-                    header: &self.operator_header,
-                    width: self.operator_width,
-                },
-                result_column: ColumnOption {
-                    header: &self.result_header,
-                    width: self.result_width,
-                },
-                operations_column: ColumnOption {
-                    header: &self.operations_header,
-                    width: self.operations_width,
-                },
-                confirmations_column: ColumnOption {
-                    header: &self.confirmations_header,
-                    width: self.confirmations_width,
-                },
-                remarks_column: ColumnOption {
-                    header: &self.remarks_header,
-                    width: self.remarks_width,
-                },
-            },
-            font_family: &self.font_family,
-            header_font_color: self.header_font_color.into_inner(),
-            header_bg_color: self.header_bg_color.into_inner(),
-            body_font_color: self.body_font_color.into_inner(),
-            body_bg_color: self.body_bg_color.into_inner(),
-            border_color: self.border_color.into_inner(),
-        }
-    }
+#[derive(Debug, Args)]
+pub struct ImportOpt {
+    /// Path to an `.xlsx` workbook, or a `.md` file in the layout of `--format markdown`
+    #[clap(name = "INPUT")]
+    pub input: String,
+
+    /// Defaults to stdout
+    #[clap(name = "OUTPUT")]
+    pub output: Option<Output>,
+
+    /// Write the results of cases with an ID to this file instead of into the spec
+    #[clap(name = "RESULTS", long = "results")]
+    pub results: Option<Output>,
+
+    #[clap(flatten)]
+    pub config: ConfigOpt,
+
+    /// Language of the headers to look for: `en`, `ja`, `zh`, `ko` or a locale file
+    /// [default: en]
+    #[clap(name = "LOCALE", long = "locale", env)]
+    pub locale: Option<String>,
+
+    #[clap(flatten)]
+    pub columns: ColumnSettings,
 }
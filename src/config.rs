@@ -0,0 +1,735 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{ensure, Context, Error};
+use clap::Args;
+use regex::Regex;
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::de::{self, IgnoredAny};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::generator::{ColumnOption, ColumnsOption, GenerateOption};
+use crate::lint::LintConfig;
+use crate::locale::Locale;
+
+pub const CONFIG_FILE_NAME: &str = "testspecgen.toml";
+
+/// Settings of a `testspecgen.toml` file or of the `config` section of a spec: the defaults,
+/// and named profiles that override them.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[schemars(deny_unknown_fields)]
+pub struct Config {
+    #[serde(flatten)]
+    pub settings: Settings,
+
+    /// Named settings applied over the defaults with `--profile`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Settings>,
+
+    /// Severities and options of the `lint` rules.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lint: Option<LintConfig>,
+}
+
+impl Config {
+    /// Finds `testspecgen.toml` in `dir` or the nearest of its ancestors.
+    pub fn discover(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|d| d.join(CONFIG_FILE_NAME))
+            .find(|p| p.is_file())
+    }
+
+    /// Reads a TOML config file. Relative paths in it are taken from the file's directory.
+    pub fn load(path: &Path) -> anyhow::Result<Config> {
+        let s = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let mut config: Config =
+            toml::from_str(&s).with_context(|| format!("failed to parse {}", path.display()))?;
+        if let Some(dir) = path.parent() {
+            config.resolve_paths(dir);
+        }
+        Ok(config)
+    }
+
+    pub fn resolve_paths(&mut self, dir: &Path) {
+        for settings in std::iter::once(&mut self.settings).chain(self.profiles.values_mut()) {
+            if let Some(font_file) = &mut settings.font_file {
+                *font_file = dir.join(&*font_file).to_string_lossy().into_owned();
+            }
+            if let Some(locale) = &mut settings.locale {
+                if Locale::builtin(locale).is_none() {
+                    *locale = dir.join(&*locale).to_string_lossy().into_owned();
+                }
+            }
+        }
+    }
+
+    /// The settings of `profile` over the defaults, or `None` if the profile is not defined
+    /// here.
+    pub fn settings(&self, profile: Option<&str>) -> Option<Settings> {
+        match profile {
+            Some(name) => Some(self.profiles.get(name)?.clone().or(self.settings.clone())),
+            None => Some(self.settings.clone()),
+        }
+    }
+}
+
+/// Output settings, given as options, environment variables or config keys.
+///
+/// Every field is optional so that the sources can be layered with [`Settings::or`]; the
+/// defaults apply when building the [`GenerateOption`].
+#[derive(Debug, Clone, Default, Args, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[clap(about = None, long_about = None)]
+#[schemars(deny_unknown_fields)]
+pub struct Settings {
+    #[clap(flatten)]
+    #[serde(flatten)]
+    pub columns: ColumnSettings,
+
+    /// Font of Excel, OpenDocument, Word and PDF output [default: Yu Gothic]
+    #[clap(name = "FONT_FAMILY", long = "font-family", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_family: Option<String>,
+
+    /// Text color of header cells [default: 0xffffff]
+    #[clap(name = "HEADER_FONT_COLOR", long = "header-font-color", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header_font_color: Option<Color>,
+
+    /// Background color of header cells [default: 0x5b9bd5]
+    #[clap(name = "HEADER_BG_COLOR", long = "header-bg-color", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header_bg_color: Option<Color>,
+
+    /// Text color of body cells [default: 0x000000]
+    #[clap(name = "BODY_FONT_COLOR", long = "body-font-color", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_font_color: Option<Color>,
+
+    /// Background color of body cells [default: 0xffffff]
+    #[clap(name = "BODY_BG_COLOR", long = "body-bg-color", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_bg_color: Option<Color>,
+
+    /// Color of cell borders [default: 0x5b9bd5]
+    #[clap(name = "BORDER_COLOR", long = "border-color", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border_color: Option<Color>,
+
+    /// Joins the lines of list cells in CSV and TSV output [default: \n]
+    #[clap(name = "LIST_SEPARATOR", long = "list-separator", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub list_separator: Option<String>,
+
+    /// Starts CSV and TSV output with a UTF-8 byte order mark so that Excel detects the
+    /// encoding
+    #[clap(
+        long = "bom",
+        value_name = "BOOL",
+        min_values = 0,
+        max_values = 1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bom: Option<bool>,
+
+    /// Turns off a `bom` set in a config file
+    #[clap(long = "no-bom", conflicts_with = "bom")]
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub no_bom: bool,
+
+    /// Font file embedded in PDF output, for when FONT_FAMILY is not installed
+    #[clap(name = "FONT_FILE", long = "font-file", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_file: Option<String>,
+
+    /// Language of the default headers and labels: `en`, `ja`, `zh`, `ko` or a locale file
+    /// [default: en]
+    #[clap(name = "LOCALE", long = "locale", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+
+    /// Takes the keys no other field took, since `deny_unknown_fields` does not work together
+    /// with `flatten`.
+    #[clap(skip)]
+    #[serde(flatten, skip_serializing, deserialize_with = "deny_unknown_settings")]
+    #[schemars(skip)]
+    pub unknown: (),
+}
+
+fn deny_unknown_settings<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(), D::Error> {
+    let keys = BTreeMap::<String, IgnoredAny>::deserialize(deserializer)?;
+    let key = match keys.into_keys().next() {
+        Some(key) => key,
+        None => return Ok(()),
+    };
+    let known: Vec<String> = setting_keys()
+        .into_iter()
+        .chain(["profiles".to_string(), "lint".to_string()])
+        .map(|key| format!("`{key}`"))
+        .collect();
+    Err(de::Error::custom(format!(
+        "unknown field `{key}`, expected one of {}",
+        known.join(", ")
+    )))
+}
+
+/// The config key of every setting.
+fn setting_keys() -> Vec<String> {
+    match Settings::json_schema(&mut SchemaGenerator::default()).get("properties") {
+        Some(serde_json::Value::Object(properties)) => properties.keys().cloned().collect(),
+        _ => unreachable!(),
+    }
+}
+
+impl Settings {
+    /// Takes each setting from `self`, else from `other`.
+    pub fn or(self, other: Settings) -> Settings {
+        let bom = self.bom().or(other.bom());
+        Settings {
+            columns: self.columns.or(other.columns),
+            font_family: self.font_family.or(other.font_family),
+            header_font_color: self.header_font_color.or(other.header_font_color),
+            header_bg_color: self.header_bg_color.or(other.header_bg_color),
+            body_font_color: self.body_font_color.or(other.body_font_color),
+            body_bg_color: self.body_bg_color.or(other.body_bg_color),
+            border_color: self.border_color.or(other.border_color),
+            list_separator: self.list_separator.or(other.list_separator),
+            bom,
+            no_bom: false,
+            font_file: self.font_file.or(other.font_file),
+            locale: self.locale.or(other.locale),
+            unknown: (),
+        }
+    }
+
+    /// Whether to write a byte order mark, if set; `--no-bom` counts as `bom = false`.
+    fn bom(&self) -> Option<bool> {
+        if self.no_bom {
+            Some(false)
+        } else {
+            self.bom
+        }
+    }
+
+    pub fn locale(&self) -> anyhow::Result<Locale> {
+        match &self.locale {
+            Some(locale) => Locale::load(locale),
+            None => Ok(Locale::english().clone()),
+        }
+    }
+
+    /// The options with the defaults of `locale` for unset headers and labels.
+    pub fn as_generate_option<'a>(&'a self, locale: &'a Locale) -> GenerateOption<'a> {
+        let default = GenerateOption::default();
+        let color = |c: Option<Color>, default| c.map_or(default, Color::into_inner);
+        GenerateOption {
+            column_options: self.columns.as_columns_option(locale),
+            font_family: self.font_family.as_deref().unwrap_or(default.font_family),
+            header_font_color: color(self.header_font_color, default.header_font_color),
+            header_bg_color: color(self.header_bg_color, default.header_bg_color),
+            body_font_color: color(self.body_font_color, default.body_font_color),
+            body_bg_color: color(self.body_bg_color, default.body_bg_color),
+            border_color: color(self.border_color, default.border_color),
+            list_separator: self
+                .list_separator
+                .as_deref()
+                .unwrap_or(default.list_separator),
+            bom: self.bom().unwrap_or(false),
+            font_file: self.font_file.as_deref(),
+            locale,
+        }
+    }
+}
+
+/// Column headers and widths of the Excel layout.
+#[derive(Debug, Clone, Default, Args, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[clap(about = None, long_about = None)]
+pub struct ColumnSettings {
+    /// Header of the number column [default: No.]
+    #[clap(name = "NO_HEADER", long = "no-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_header: Option<String>,
+
+    /// Width of the number column [default: 8]
+    #[clap(name = "NO_WIDTH", long = "no-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_width: Option<f64>,
+
+    /// Header of the primary item column [default: Primary Item]
+    #[clap(name = "PRIMARY_ITEM_HEADER", long = "primary-item-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_item_header: Option<String>,
+
+    /// Width of the primary item column [default: 16]
+    #[clap(name = "PRIMARY_ITEM_WIDTH", long = "primary-item-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary_item_width: Option<f64>,
+
+    /// Header of the secondary item column [default: Secondary Item]
+    #[clap(name = "SECONDARY_ITEM_HEADER", long = "secondary-item-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secondary_item_header: Option<String>,
+
+    /// Width of the secondary item column [default: 16]
+    #[clap(name = "SECONDARY_ITEM_WIDTH", long = "secondary-item-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secondary_item_width: Option<f64>,
+
+    /// Header of the tertiary item column [default: Tertiary Item]
+    #[clap(name = "TERTIARY_ITEM_HEADER", long = "tertiary-item-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tertiary_item_header: Option<String>,
+
+    /// Width of the tertiary item column [default: 16]
+    #[clap(name = "TERTIARY_ITEM_WIDTH", long = "tertiary-item-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tertiary_item_width: Option<f64>,
+
+    /// Headers of the item columns below the tertiary one, one per extra level
+    /// [default: Quaternary Item,Quinary Item]
+    #[clap(
+        name = "EXTRA_ITEM_HEADERS",
+        long = "extra-item-headers",
+        value_delimiter = ',',
+        env
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_item_headers: Option<Vec<String>>,
+
+    /// Width of each extra item column [default: 16]
+    #[clap(name = "EXTRA_ITEM_WIDTH", long = "extra-item-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_item_width: Option<f64>,
+
+    /// Header of the operator column [default: Operator]
+    #[clap(name = "OPERATOR_HEADER", long = "operator-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator_header: Option<String>,
+
+    /// Width of the operator column [default: 12]
+    #[clap(name = "OPERATOR_WIDTH", long = "operator-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator_width: Option<f64>,
+
+    /// Header of the result column [default: Result]
+    #[clap(name = "RESULT_HEADER", long = "result-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_header: Option<String>,
+
+    /// Width of the result column [default: 8]
+    #[clap(name = "RESULT_WIDTH", long = "result-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_width: Option<f64>,
+
+    /// Header of the date column [default: Date]
+    #[clap(name = "DATE_HEADER", long = "date-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_header: Option<String>,
+
+    /// Width of the date column [default: 12]
+    #[clap(name = "DATE_WIDTH", long = "date-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_width: Option<f64>,
+
+    /// Header of the note column [default: Note]
+    #[clap(name = "NOTE_HEADER", long = "note-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note_header: Option<String>,
+
+    /// Width of the note column [default: 30]
+    #[clap(name = "NOTE_WIDTH", long = "note-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note_width: Option<f64>,
+
+    /// Header of the defect column [default: Defect]
+    #[clap(name = "DEFECT_HEADER", long = "defect-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defect_header: Option<String>,
+
+    /// Width of the defect column [default: 16]
+    #[clap(name = "DEFECT_WIDTH", long = "defect-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defect_width: Option<f64>,
+
+    /// Header of the environment column [default: Environment]
+    #[clap(name = "ENVIRONMENT_HEADER", long = "environment-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment_header: Option<String>,
+
+    /// Width of the environment column [default: 30]
+    #[clap(name = "ENVIRONMENT_WIDTH", long = "environment-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment_width: Option<f64>,
+
+    /// Header of the preconditions column [default: Preconditions]
+    #[clap(name = "PRECONDITIONS_HEADER", long = "preconditions-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preconditions_header: Option<String>,
+
+    /// Width of the preconditions column [default: 40]
+    #[clap(name = "PRECONDITIONS_WIDTH", long = "preconditions-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preconditions_width: Option<f64>,
+
+    /// Header of the test data column [default: Test Data]
+    #[clap(name = "TEST_DATA_HEADER", long = "test-data-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test_data_header: Option<String>,
+
+    /// Width of the test data column [default: 30]
+    #[clap(name = "TEST_DATA_WIDTH", long = "test-data-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test_data_width: Option<f64>,
+
+    /// Header of the parameters column [default: Parameters]
+    #[clap(name = "PARAMETERS_HEADER", long = "parameters-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters_header: Option<String>,
+
+    /// Width of the parameters column [default: 30]
+    #[clap(name = "PARAMETERS_WIDTH", long = "parameters-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters_width: Option<f64>,
+
+    /// Header of the operations column [default: Operations]
+    #[clap(name = "OPERATIONS_HEADER", long = "operations-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operations_header: Option<String>,
+
+    /// Width of the operations column [default: 60]
+    #[clap(name = "OPERATIONS_ITEM_WIDTH", long = "operations-item-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operations_width: Option<f64>,
+
+    /// Header of the confirmations column [default: Confirmations]
+    #[clap(name = "CONFIRMATIONS_HEADER", long = "confirmations-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmations_header: Option<String>,
+
+    /// Width of the confirmations column [default: 60]
+    #[clap(
+        name = "CONFIRMATIONS_ITEM_WIDTH",
+        long = "confirmations-item-width",
+        env
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmations_width: Option<f64>,
+
+    /// Header of the remarks column [default: Remarks]
+    #[clap(name = "REMARKS_HEADER", long = "remarks-header", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remarks_header: Option<String>,
+
+    /// Width of the remarks column [default: 60]
+    #[clap(name = "REMARKS_ITEM_WIDTH", long = "remarks-item-width", env)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remarks_width: Option<f64>,
+}
+
+impl ColumnSettings {
+    /// Takes each setting from `self`, else from `other`.
+    pub fn or(self, other: ColumnSettings) -> ColumnSettings {
+        ColumnSettings {
+            no_header: self.no_header.or(other.no_header),
+            no_width: self.no_width.or(other.no_width),
+            primary_item_header: self.primary_item_header.or(other.primary_item_header),
+            primary_item_width: self.primary_item_width.or(other.primary_item_width),
+            secondary_item_header: self.secondary_item_header.or(other.secondary_item_header),
+            secondary_item_width: self.secondary_item_width.or(other.secondary_item_width),
+            tertiary_item_header: self.tertiary_item_header.or(other.tertiary_item_header),
+            tertiary_item_width: self.tertiary_item_width.or(other.tertiary_item_width),
+            extra_item_headers: self.extra_item_headers.or(other.extra_item_headers),
+            extra_item_width: self.extra_item_width.or(other.extra_item_width),
+            operator_header: self.operator_header.or(other.operator_header),
+            operator_width: self.operator_width.or(other.operator_width),
+            result_header: self.result_header.or(other.result_header),
+            result_width: self.result_width.or(other.result_width),
+            date_header: self.date_header.or(other.date_header),
+            date_width: self.date_width.or(other.date_width),
+            note_header: self.note_header.or(other.note_header),
+            note_width: self.note_width.or(other.note_width),
+            defect_header: self.defect_header.or(other.defect_header),
+            defect_width: self.defect_width.or(other.defect_width),
+            environment_header: self.environment_header.or(other.environment_header),
+            environment_width: self.environment_width.or(other.environment_width),
+            preconditions_header: self.preconditions_header.or(other.preconditions_header),
+            preconditions_width: self.preconditions_width.or(other.preconditions_width),
+            test_data_header: self.test_data_header.or(other.test_data_header),
+            test_data_width: self.test_data_width.or(other.test_data_width),
+            parameters_header: self.parameters_header.or(other.parameters_header),
+            parameters_width: self.parameters_width.or(other.parameters_width),
+            operations_header: self.operations_header.or(other.operations_header),
+            operations_width: self.operations_width.or(other.operations_width),
+            confirmations_header: self.confirmations_header.or(other.confirmations_header),
+            confirmations_width: self.confirmations_width.or(other.confirmations_width),
+            remarks_header: self.remarks_header.or(other.remarks_header),
+            remarks_width: self.remarks_width.or(other.remarks_width),
+        }
+    }
+
+    pub fn as_columns_option<'a>(&'a self, locale: &'a Locale) -> ColumnsOption<'a> {
+        let default = ColumnsOption::from_locale(locale);
+        let mut defaults = default.item_columns.into_iter();
+        let mut item_column = |header, width| match defaults.next() {
+            Some(d) => column(header, width, d),
+            None => unreachable!(),
+        };
+        let item_columns = vec![
+            item_column(&self.primary_item_header, self.primary_item_width),
+            item_column(&self.secondary_item_header, self.secondary_item_width),
+            item_column(&self.tertiary_item_header, self.tertiary_item_width),
+        ];
+        let extra_width = self
+            .extra_item_width
+            .unwrap_or(defaults.as_slice().first().map_or(16.0, |d| d.width));
+        let extra_columns: Vec<ColumnOption> = match &self.extra_item_headers {
+            Some(headers) => headers
+                .iter()
+                .map(|h| ColumnOption {
+                    header: h,
+                    width: extra_width,
+                })
+                .collect(),
+            None => defaults
+                .map(|d| ColumnOption {
+                    header: d.header,
+                    width: extra_width,
+                })
+                .collect(),
+        };
+
+        ColumnsOption {
+            no_column: column(&self.no_header, self.no_width, default.no_column),
+            item_columns: item_columns.into_iter().chain(extra_columns).collect(),
+            operator_column: column(
+                &self.operator_header,
+                self.operator_width,
+                default.operator_column,
+            ),
+            result_column: column(
+                &self.result_header,
+                self.result_width,
+                default.result_column,
+            ),
+            date_column: column(&self.date_header, self.date_width, default.date_column),
+            note_column: column(&self.note_header, self.note_width, default.note_column),
+            defect_column: column(
+                &self.defect_header,
+                self.defect_width,
+                default.defect_column,
+            ),
+            environment_column: column(
+                &self.environment_header,
+                self.environment_width,
+                default.environment_column,
+            ),
+            preconditions_column: column(
+                &self.preconditions_header,
+                self.preconditions_width,
+                default.preconditions_column,
+            ),
+            test_data_column: column(
+                &self.test_data_header,
+                self.test_data_width,
+                default.test_data_column,
+            ),
+            parameters_column: column(
+                &self.parameters_header,
+                self.parameters_width,
+                default.parameters_column,
+            ),
+            operations_column: column(
+                &self.operations_header,
+                self.operations_width,
+                default.operations_column,
+            ),
+            confirmations_column: column(
+                &self.confirmations_header,
+                self.confirmations_width,
+                default.confirmations_column,
+            ),
+            remarks_column: column(
+                &self.remarks_header,
+                self.remarks_width,
+                default.remarks_column,
+            ),
+        }
+    }
+}
+
+fn column<'a>(
+    header: &'a Option<String>,
+    width: Option<f64>,
+    default: ColumnOption<'a>,
+) -> ColumnOption<'a> {
+    ColumnOption {
+        header: header.as_deref().unwrap_or(default.header),
+        width: width.unwrap_or(default.width),
+    }
+}
+
+/// An RGB color written as `0xrrggbb`.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Color(u32);
+
+impl Color {
+    pub fn into_inner(self) -> u32 {
+        self.0
+    }
+}
+
+const COLOR_PATTERN: &str = "^0x[0-9a-fA-F]{6}$";
+
+impl JsonSchema for Color {
+    fn schema_name() -> Cow<'static, str> {
+        "Color".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "description": "An RGB color written as `0xrrggbb`",
+            "pattern": COLOR_PATTERN,
+            "examples": ["0x5b9bd5"],
+        })
+    }
+}
+
+impl FromStr for Color {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Color, Self::Err> {
+        let regex = match Regex::new(COLOR_PATTERN) {
+            Ok(r) => r,
+            Err(_) => unreachable!(),
+        };
+        ensure!(regex.is_match(s), "invalid color: {s}");
+        let c = match u32::from_str_radix(&s[2..], 16) {
+            Ok(c) => c,
+            Err(_) => unreachable!(),
+        };
+        Ok(Color(c))
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = Error;
+    fn try_from(s: String) -> Result<Color, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Color> for String {
+    fn from(c: Color) -> String {
+        format!("{:#08x}", c.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn layering() {
+        let config: Config = toml::from_str(
+            r#"
+font_family = "Meiryo"
+header_bg_color = "0x112233"
+operations_header = "Steps"
+extra_item_width = 20
+
+[profiles.client-a]
+header_bg_color = "0xff0000"
+no_header = "No"
+locale = "ja"
+"#,
+        )
+        .unwrap();
+
+        let cli = Settings {
+            font_family: Some("Noto Sans JP".to_string()),
+            ..Default::default()
+        };
+        let settings = cli.or(config.settings(Some("client-a")).unwrap());
+        let locale = settings.locale().unwrap();
+        let option = settings.as_generate_option(&locale);
+        assert_eq!("Noto Sans JP", option.font_family);
+        assert_eq!(0xff0000, option.header_bg_color);
+        assert_eq!(0xffffff, option.header_font_color);
+        assert_eq!("No", option.column_options.no_column.header);
+        assert_eq!("Steps", option.column_options.operations_column.header);
+        assert_eq!(60.0, option.column_options.operations_column.width);
+        assert_eq!(
+            "確認項目",
+            option.column_options.confirmations_column.header
+        );
+        assert_eq!("詳細", option.column_options.item_columns[4].header);
+        assert_eq!(20.0, option.column_options.item_columns[4].width);
+
+        assert_eq!(
+            0x112233,
+            config
+                .settings(None)
+                .unwrap()
+                .as_generate_option(Locale::english())
+                .header_bg_color
+        );
+        assert!(config.settings(Some("internal")).is_none());
+        assert_eq!(
+            "0x5b9bd5",
+            String::from("0x5b9bd5".parse::<Color>().unwrap())
+        );
+    }
+
+    #[test]
+    fn boolean_precedence() {
+        let config: Config = toml::from_str("bom = true\n[profiles.plain]\nbom = false\n").unwrap();
+        let bom = |cli: Settings, profile| {
+            cli.or(config.settings(profile).unwrap())
+                .as_generate_option(Locale::english())
+                .bom
+        };
+
+        assert!(bom(Settings::default(), None));
+        assert!(!bom(Settings::default(), Some("plain")));
+        let on = Settings {
+            bom: Some(true),
+            ..Default::default()
+        };
+        assert!(bom(on, Some("plain")));
+        let off = Settings {
+            no_bom: true,
+            ..Default::default()
+        };
+        assert!(!bom(off.clone(), None));
+        assert!(!off.as_generate_option(Locale::english()).bom);
+    }
+
+    #[test]
+    fn discovery() {
+        let temp = mktemp::Temp::new_dir().unwrap();
+        let nested = temp.join("specs").join("login");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(
+            None,
+            Config::discover(&nested).filter(|p| p.starts_with(&*temp))
+        );
+
+        let path = temp.join(CONFIG_FILE_NAME);
+        fs::write(&path, "font_file = \"fonts/font.otf\"\n").unwrap();
+        assert_eq!(Some(path.clone()), Config::discover(&nested));
+
+        let config = Config::load(&path).unwrap();
+        assert_eq!(
+            Some(temp.join("fonts/font.otf").to_string_lossy().into_owned()),
+            config.settings.font_file
+        );
+    }
+}
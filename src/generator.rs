@@ -1,10 +1,30 @@
+mod delimited;
+mod docx;
 mod excel;
+mod gherkin;
+mod html;
+mod junit;
 mod markdown;
+mod ods;
+mod pdf;
+mod table;
+mod template;
 
-use std::array::IntoIter;
+use anyhow::ensure;
 
+use crate::locale::Locale;
+use crate::testspec::TestSpec;
+
+pub use delimited::generate_delimited;
+pub use docx::generate_docx;
 pub use excel::generate_excel;
+pub use gherkin::generate_gherkin;
+pub use html::generate_html;
+pub use junit::generate_junit;
 pub use markdown::generate_markdown;
+pub use ods::generate_ods;
+pub use pdf::generate_pdf;
+pub use template::generate_template;
 
 #[derive(Debug)]
 pub struct GenerateOption<'a> {
@@ -15,6 +35,14 @@ pub struct GenerateOption<'a> {
     pub body_font_color: u32,
     pub body_bg_color: u32,
     pub border_color: u32,
+    /// Joins the lines of list cells in delimited output.
+    pub list_separator: &'a str,
+    /// Whether delimited output starts with a UTF-8 byte order mark.
+    pub bom: bool,
+    /// Font embedded in PDF output instead of the installed `font_family`.
+    pub font_file: Option<&'a str>,
+    /// Section labels and sheet names; the column headers are in `column_options`.
+    pub locale: &'a Locale,
 }
 
 impl Default for GenerateOption<'_> {
@@ -27,6 +55,10 @@ impl Default for GenerateOption<'_> {
             body_font_color: 0x000000,
             body_bg_color: 0xffffff,
             border_color: 0x5b9bd5,
+            list_separator: "\n",
+            bom: false,
+            font_file: None,
+            locale: Locale::english(),
         }
     }
 }
@@ -34,80 +66,181 @@ impl Default for GenerateOption<'_> {
 #[derive(Debug)]
 pub struct ColumnsOption<'a> {
     pub no_column: ColumnOption<'a>,
-    pub primary_item_column: ColumnOption<'a>,
-    pub secondary_item_column: ColumnOption<'a>,
-    pub tertiary_item_column: ColumnOption<'a>,
+    pub item_columns: Vec<ColumnOption<'a>>,
     pub operator_column: ColumnOption<'a>,
     pub result_column: ColumnOption<'a>,
+    pub date_column: ColumnOption<'a>,
+    pub note_column: ColumnOption<'a>,
+    pub defect_column: ColumnOption<'a>,
+    pub environment_column: ColumnOption<'a>,
+    pub preconditions_column: ColumnOption<'a>,
+    pub test_data_column: ColumnOption<'a>,
+    pub parameters_column: ColumnOption<'a>,
     pub operations_column: ColumnOption<'a>,
     pub confirmations_column: ColumnOption<'a>,
     pub remarks_column: ColumnOption<'a>,
 }
 
-impl<'a> IntoIterator for &'a ColumnsOption<'_> {
-    type Item = &'a ColumnOption<'a>;
-    type IntoIter = IntoIter<Self::Item, 9>;
-    fn into_iter(self) -> <Self as IntoIterator>::IntoIter {
-        [
-            &self.no_column,
-            &self.primary_item_column,
-            &self.secondary_item_column,
-            &self.tertiary_item_column,
-            &self.operator_column,
-            &self.result_column,
-            &self.operations_column,
-            &self.confirmations_column,
-            &self.remarks_column,
-        ]
-        .into_iter()
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Column {
+    No,
+    /// The item column of a level, counted from 0.
+    Item(usize),
+    Operator,
+    Result,
+    Date,
+    Note,
+    Defect,
+    Environment,
+    Preconditions,
+    TestData,
+    Parameters,
+    Operations,
+    Confirmations,
+    Remarks,
+}
+
+impl Column {
+    /// Whether the cells hold free text, which is left-aligned rather than centered.
+    pub fn is_text(self) -> bool {
+        matches!(
+            self,
+            Column::Note
+                | Column::Environment
+                | Column::Preconditions
+                | Column::TestData
+                | Column::Parameters
+                | Column::Operations
+                | Column::Confirmations
+                | Column::Remarks
+        )
     }
 }
 
-impl Default for ColumnsOption<'_> {
-    fn default() -> Self {
+impl<'a> ColumnsOption<'a> {
+    /// Returns the columns of `spec` in sheet order: one item column per level, and the
+    /// optional columns (execution date, note, defect, environment, preconditions, test data
+    /// and parameters) only if some item uses them.
+    pub fn columns(&self, spec: &TestSpec) -> anyhow::Result<Vec<(Column, &ColumnOption<'a>)>> {
+        ensure!(
+            spec.depth <= self.item_columns.len(),
+            "no item column configured for level {}",
+            self.item_columns.len() + 1
+        );
+
+        let items = spec.items();
+        let has_environment = items.iter().any(|i| !i.environment.is_empty());
+        let has_preconditions = items.iter().any(|i| !i.preconditions.is_empty());
+        let has_test_data = items.iter().any(|i| !i.test_data.is_empty());
+        let has_parameters = items
+            .iter()
+            .any(|i| !i.parameters.is_empty() || !i.examples.is_empty());
+        let results: Vec<_> = items.iter().filter_map(|i| i.result.as_ref()).collect();
+        let has_date = results.iter().any(|r| r.date.is_some());
+        let has_note = results.iter().any(|r| r.note.is_some());
+        let has_defect = results.iter().any(|r| r.defect.is_some());
+
+        let mut columns = vec![(Column::No, &self.no_column)];
+        columns.extend(
+            self.item_columns[..spec.depth]
+                .iter()
+                .enumerate()
+                .map(|(level, o)| (Column::Item(level), o)),
+        );
+        columns.push((Column::Operator, &self.operator_column));
+        columns.push((Column::Result, &self.result_column));
+        if has_date {
+            columns.push((Column::Date, &self.date_column));
+        }
+        if has_note {
+            columns.push((Column::Note, &self.note_column));
+        }
+        if has_defect {
+            columns.push((Column::Defect, &self.defect_column));
+        }
+        if has_environment {
+            columns.push((Column::Environment, &self.environment_column));
+        }
+        if has_preconditions {
+            columns.push((Column::Preconditions, &self.preconditions_column));
+        }
+        if has_test_data {
+            columns.push((Column::TestData, &self.test_data_column));
+        }
+        if has_parameters {
+            columns.push((Column::Parameters, &self.parameters_column));
+        }
+        columns.push((Column::Operations, &self.operations_column));
+        columns.push((Column::Confirmations, &self.confirmations_column));
+        columns.push((Column::Remarks, &self.remarks_column));
+        Ok(columns)
+    }
+}
+
+impl<'a> ColumnsOption<'a> {
+    /// The headers of `locale` with the default widths.
+    pub fn from_locale(locale: &'a Locale) -> Self {
+        let labels = &locale.columns;
+        let column = |header: &'a String, width| ColumnOption { header, width };
         ColumnsOption {
-            no_column: ColumnOption {
-                header: "No.",
-                width: 8f64,
-            },
-            primary_item_column: ColumnOption {
-                header: "Primary Item",
-                width: 16f64,
-            },
-            secondary_item_column: ColumnOption {
-                header: "Secondary Item",
-                width: 16f64,
-            },
-            tertiary_item_column: ColumnOption {
-                header: "Tertiary Item",
-                width: 16f64,
-            },
-            operator_column: ColumnOption {
-                header: "Operator",
-                width: 12f64,
-            },
-            result_column: ColumnOption {
-                header: "Result",
-                width: 8f64,
-            },
-            operations_column: ColumnOption {
-                header: "Operations",
-                width: 60f64,
-            },
-            confirmations_column: ColumnOption {
-                header: "Confirmations",
-                width: 60f64,
-            },
-            remarks_column: ColumnOption {
-                header: "Remarks",
-                width: 60f64,
-            },
+            no_column: column(&labels.no, 8f64),
+            item_columns: [
+                &labels.primary_item,
+                &labels.secondary_item,
+                &labels.tertiary_item,
+            ]
+            .into_iter()
+            .chain(&labels.extra_items)
+            .map(|header| column(header, 16f64))
+            .collect(),
+            operator_column: column(&labels.operator, 12f64),
+            result_column: column(&labels.result, 8f64),
+            date_column: column(&labels.date, 12f64),
+            note_column: column(&labels.note, 30f64),
+            defect_column: column(&labels.defect, 16f64),
+            environment_column: column(&labels.environment, 30f64),
+            preconditions_column: column(&labels.preconditions, 40f64),
+            test_data_column: column(&labels.test_data, 30f64),
+            parameters_column: column(&labels.parameters, 30f64),
+            operations_column: column(&labels.operations, 60f64),
+            confirmations_column: column(&labels.confirmations, 60f64),
+            remarks_column: column(&labels.remarks, 60f64),
         }
     }
 }
 
+impl Default for ColumnsOption<'_> {
+    fn default() -> Self {
+        ColumnsOption::from_locale(Locale::english())
+    }
+}
+
 #[derive(Debug)]
 pub struct ColumnOption<'a> {
     pub header: &'a str,
     pub width: f64,
 }
+
+/// The spec title if it can name a sheet, else the sheet name of the locale. Sheet names have
+/// at most 31 characters, none of `[]:*?/\`, and cannot start or end with `'`.
+fn sheet_name<'a>(spec: &'a TestSpec, locale: &'a Locale) -> &'a str {
+    let title = &spec.title;
+    let valid = !title.is_empty()
+        && title.chars().count() <= 31
+        && !title.contains(['[', ']', ':', '*', '?', '/', '\\'])
+        && !title.starts_with('\'')
+        && !title.ends_with('\'');
+    if valid {
+        title
+    } else {
+        &locale.sheets.cases
+    }
+}
+
+/// Escapes text for HTML and XML content and attribute values.
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
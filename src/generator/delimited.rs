@@ -0,0 +1,99 @@
+use crate::testspec::TestSpec;
+
+use super::table::{case_text, rows};
+use super::{Column, GenerateOption};
+
+/// Generates CSV (`delimiter` `b','`) or TSV (`b'\t'`) with the Excel columns. Item titles
+/// are repeated on every row instead of merged, and the lines of list cells are joined with
+/// `option.list_separator`.
+pub fn generate_delimited(
+    spec: &TestSpec,
+    option: &GenerateOption,
+    delimiter: u8,
+) -> anyhow::Result<Vec<u8>> {
+    let columns = option.column_options.columns(spec)?;
+
+    let mut buf = Vec::new();
+    if option.bom {
+        buf.extend_from_slice("\u{feff}".as_bytes());
+    }
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(buf);
+
+    writer.write_record(columns.iter().map(|(_, o)| o.header))?;
+
+    for (i, row) in rows(spec).iter().enumerate() {
+        let record = columns
+            .iter()
+            .map(|(column, _)| match (column, row.case()) {
+                (Column::No, _) => match &row.item().id {
+                    Some(id) => id.clone(),
+                    None => (i + 1).to_string(),
+                },
+                (Column::Item(level), _) => row.items[*level]
+                    .map(|item| item.title.clone())
+                    .unwrap_or_default(),
+                (column, Some(case)) => case_text(*column, case)
+                    .unwrap_or_default()
+                    .lines()
+                    .collect::<Vec<_>>()
+                    .join(option.list_separator),
+                (_, None) => String::new(),
+            });
+        writer.write_record(record)?;
+    }
+
+    Ok(writer.into_inner()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = "
+title: Spec title
+depth: 2
+
+cases:
+  - title: ログイン
+    children:
+      - id: TC-1
+        title: Valid, \"quoted\" password
+        operations:
+          - パスワードを入力する
+          - Press the login button
+      - title: Invalid password
+";
+
+    #[test]
+    fn csv() {
+        let spec: TestSpec = SPEC.parse().unwrap();
+        let bytes = generate_delimited(&spec, &GenerateOption::default(), b',').unwrap();
+        let expected =
+            "No.,Primary Item,Secondary Item,Operator,Result,Operations,Confirmations,Remarks
+TC-1,ログイン,\"Valid, \"\"quoted\"\" password\",,,\"1. パスワードを入力する
+2. Press the login button\",,
+2,ログイン,Invalid password,,,,,
+";
+        assert_eq!(expected, String::from_utf8(bytes).unwrap());
+    }
+
+    #[test]
+    fn tsv_with_separator_and_bom() {
+        let spec: TestSpec = SPEC.parse().unwrap();
+        let option = GenerateOption {
+            list_separator: " / ",
+            bom: true,
+            ..Default::default()
+        };
+        let bytes = generate_delimited(&spec, &option, b'\t').unwrap();
+        let tsv = String::from_utf8(bytes).unwrap();
+        let lines: Vec<_> = tsv.lines().collect();
+        assert!(lines[0].starts_with("\u{feff}No.\tPrimary Item\t"));
+        assert_eq!(
+            "TC-1\tログイン\t\"Valid, \"\"quoted\"\" password\"\t\t\t1. パスワードを入力する / 2. Press the login button\t\t",
+            lines[1]
+        );
+    }
+}
@@ -0,0 +1,356 @@
+use std::fmt::Write as _;
+use std::io::{Cursor, Write as _};
+
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::testspec::{Item, TestSpec};
+
+use super::table::case_text;
+use super::{escape, Column, GenerateOption};
+
+const W: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+
+const CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>
+<Default Extension="xml" ContentType="application/xml"/>
+<Override PartName="/word/document.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.document.main+xml"/>
+<Override PartName="/word/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.styles+xml"/>
+<Override PartName="/word/numbering.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.numbering+xml"/>
+<Override PartName="/word/settings.xml" ContentType="application/vnd.openxmlformats-officedocument.wordprocessingml.settings+xml"/>
+</Types>
+"#;
+
+const RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="word/document.xml"/>
+</Relationships>
+"#;
+
+const DOCUMENT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>
+<Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/numbering" Target="numbering.xml"/>
+<Relationship Id="rId3" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/settings" Target="settings.xml"/>
+</Relationships>
+"#;
+
+/// Asks Word to refresh the table of contents when the document is opened.
+const SETTINGS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:settings xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+<w:updateFields w:val="true"/>
+</w:settings>
+"#;
+
+/// Word supports nine heading levels.
+const MAX_HEADING: usize = 9;
+
+/// Generates a Word document: a title page, a table of contents, a numbered heading per
+/// item above the test cases, and a table per test case listing its fields under the
+/// configured column headers. Fonts and colors are those of the Excel layout.
+pub fn generate_docx(spec: &TestSpec, option: &GenerateOption) -> anyhow::Result<Vec<u8>> {
+    let document = document(spec, option)?;
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    for (name, content) in [
+        ("[Content_Types].xml", CONTENT_TYPES.to_string()),
+        ("_rels/.rels", RELS.to_string()),
+        ("word/_rels/document.xml.rels", DOCUMENT_RELS.to_string()),
+        ("word/document.xml", document),
+        ("word/styles.xml", styles(option)?),
+        ("word/numbering.xml", numbering()?),
+        ("word/settings.xml", SETTINGS.to_string()),
+    ] {
+        zip.start_file(name, options)?;
+        zip.write_all(content.as_bytes())?;
+    }
+
+    Ok(zip.finish()?.into_inner())
+}
+
+fn document(spec: &TestSpec, option: &GenerateOption) -> anyhow::Result<String> {
+    let mut buf = String::new();
+    writeln!(
+        buf,
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#
+    )?;
+    writeln!(buf, r#"<w:document xmlns:w="{W}">"#)?;
+    writeln!(buf, "<w:body>")?;
+
+    writeln!(
+        buf,
+        r#"<w:p><w:pPr><w:pStyle w:val="Title"/></w:pPr>{}</w:p>"#,
+        run(&spec.title)
+    )?;
+    writeln!(buf, r#"<w:p><w:r><w:br w:type="page"/></w:r></w:p>"#)?;
+
+    let levels = spec.depth.saturating_sub(1).clamp(1, MAX_HEADING);
+    writeln!(
+        buf,
+        r#"<w:p><w:pPr><w:pStyle w:val="TOCHeading"/></w:pPr>{}</w:p>"#,
+        run(&option.locale.sections.contents)
+    )?;
+    writeln!(
+        buf,
+        r#"<w:p><w:r><w:fldChar w:fldCharType="begin" w:dirty="true"/></w:r><w:r><w:instrText xml:space="preserve"> TOC \o "1-{levels}" \h \z \u </w:instrText></w:r><w:r><w:fldChar w:fldCharType="separate"/></w:r>{}<w:r><w:fldChar w:fldCharType="end"/></w:r></w:p>"#,
+        run("Update the field to show the table of contents.")
+    )?;
+    writeln!(buf, r#"<w:p><w:r><w:br w:type="page"/></w:r></w:p>"#)?;
+
+    let mut writer = Writer {
+        buf: &mut buf,
+        option,
+        depth: spec.depth,
+    };
+    writer.write_items(&spec.cases, 1)?;
+
+    writeln!(
+        buf,
+        r#"<w:sectPr><w:pgSz w:w="11906" w:h="16838"/><w:pgMar w:top="1440" w:right="1134" w:bottom="1440" w:left="1134" w:header="720" w:footer="720" w:gutter="0"/></w:sectPr>"#
+    )?;
+    writeln!(buf, "</w:body>")?;
+    writeln!(buf, "</w:document>")?;
+    Ok(buf)
+}
+
+struct Writer<'a, 'b> {
+    buf: &'a mut String,
+    option: &'a GenerateOption<'b>,
+    depth: usize,
+}
+
+impl Writer<'_, '_> {
+    fn write_items(&mut self, items: &[Item], level: usize) -> std::fmt::Result {
+        for item in items {
+            if level == self.depth {
+                self.write_case(item)?;
+                continue;
+            }
+            writeln!(
+                self.buf,
+                r#"<w:p><w:pPr><w:pStyle w:val="Heading{}"/></w:pPr>{}</w:p>"#,
+                level.min(MAX_HEADING),
+                run(&item.title)
+            )?;
+            self.write_items(&item.children, level + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a two-column table: the case title across the top, then a row per field
+    /// with the column header as its label.
+    fn write_case(&mut self, case: &Item) -> std::fmt::Result {
+        let columns = &self.option.column_options;
+        let mut rows = Vec::new();
+        if let Some(id) = &case.id {
+            rows.push((columns.no_column.header, id.clone()));
+        }
+        for (column, o) in [
+            (Column::Environment, &columns.environment_column),
+            (Column::Preconditions, &columns.preconditions_column),
+            (Column::TestData, &columns.test_data_column),
+            (Column::Parameters, &columns.parameters_column),
+            (Column::Operations, &columns.operations_column),
+            (Column::Confirmations, &columns.confirmations_column),
+            (Column::Remarks, &columns.remarks_column),
+            (Column::Result, &columns.result_column),
+            (Column::Operator, &columns.operator_column),
+            (Column::Date, &columns.date_column),
+            (Column::Note, &columns.note_column),
+            (Column::Defect, &columns.defect_column),
+        ] {
+            let text = case_text(column, case).unwrap_or_default();
+            if !text.is_empty() {
+                rows.push((o.header, text));
+            }
+        }
+
+        let border = format!(
+            r#"w:val="single" w:sz="12" w:space="0" w:color="{:06X}""#,
+            self.option.border_color
+        );
+        writeln!(self.buf, "<w:tbl>")?;
+        writeln!(
+            self.buf,
+            r#"<w:tblPr><w:tblW w:w="5000" w:type="pct"/><w:tblBorders><w:top {border}/><w:left {border}/><w:bottom {border}/><w:right {border}/><w:insideH {border}/><w:insideV {border}/></w:tblBorders></w:tblPr>"#
+        )?;
+        writeln!(
+            self.buf,
+            r#"<w:tblGrid><w:gridCol w:w="2268"/><w:gridCol w:w="7370"/></w:tblGrid>"#
+        )?;
+
+        write!(self.buf, r#"<w:tr><w:trPr><w:cantSplit/></w:trPr>"#)?;
+        self.write_cell(&case.title, true, 2)?;
+        writeln!(self.buf, "</w:tr>")?;
+        for (label, text) in rows {
+            write!(self.buf, r#"<w:tr><w:trPr><w:cantSplit/></w:trPr>"#)?;
+            self.write_cell(label, true, 1)?;
+            self.write_cell(&text, false, 1)?;
+            writeln!(self.buf, "</w:tr>")?;
+        }
+        writeln!(self.buf, "</w:tbl>")?;
+        // Keeps consecutive tables apart.
+        writeln!(self.buf, "<w:p/>")
+    }
+
+    fn write_cell(&mut self, text: &str, header: bool, span: usize) -> std::fmt::Result {
+        let (font, bg) = if header {
+            (self.option.header_font_color, self.option.header_bg_color)
+        } else {
+            (self.option.body_font_color, self.option.body_bg_color)
+        };
+        let bold = if header { "<w:b/>" } else { "" };
+
+        write!(self.buf, "<w:tc><w:tcPr>")?;
+        if span > 1 {
+            write!(self.buf, r#"<w:gridSpan w:val="{span}"/>"#)?;
+        }
+        write!(
+            self.buf,
+            r#"<w:shd w:val="clear" w:color="auto" w:fill="{bg:06X}"/></w:tcPr>"#
+        )?;
+        for line in text.lines() {
+            write!(
+                self.buf,
+                r#"<w:p><w:r><w:rPr>{bold}<w:color w:val="{font:06X}"/></w:rPr><w:t xml:space="preserve">{}</w:t></w:r></w:p>"#,
+                escape(line)
+            )?;
+        }
+        if text.is_empty() {
+            write!(self.buf, "<w:p/>")?;
+        }
+        write!(self.buf, "</w:tc>")
+    }
+}
+
+fn run(text: &str) -> String {
+    format!(
+        r#"<w:r><w:t xml:space="preserve">{}</w:t></w:r>"#,
+        escape(text)
+    )
+}
+
+/// The document font and colors, the title style, and heading styles numbered through
+/// `numbering.xml` so that they appear in the table of contents.
+fn styles(option: &GenerateOption) -> Result<String, std::fmt::Error> {
+    let font = escape(option.font_family);
+    let mut buf = String::new();
+    writeln!(
+        buf,
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#
+    )?;
+    writeln!(buf, r#"<w:styles xmlns:w="{W}">"#)?;
+    writeln!(
+        buf,
+        r#"<w:docDefaults><w:rPrDefault><w:rPr><w:rFonts w:ascii="{font}" w:hAnsi="{font}" w:eastAsia="{font}" w:cs="{font}"/><w:color w:val="{:06X}"/><w:sz w:val="21"/></w:rPr></w:rPrDefault><w:pPrDefault><w:pPr><w:spacing w:after="60"/></w:pPr></w:pPrDefault></w:docDefaults>"#,
+        option.body_font_color
+    )?;
+    writeln!(
+        buf,
+        r#"<w:style w:type="paragraph" w:default="1" w:styleId="Normal"><w:name w:val="Normal"/></w:style>"#
+    )?;
+    writeln!(
+        buf,
+        r#"<w:style w:type="paragraph" w:styleId="Title"><w:name w:val="Title"/><w:basedOn w:val="Normal"/><w:pPr><w:spacing w:before="4000" w:after="400"/><w:jc w:val="center"/></w:pPr><w:rPr><w:b/><w:color w:val="{:06X}"/><w:sz w:val="56"/></w:rPr></w:style>"#,
+        option.header_bg_color
+    )?;
+    writeln!(
+        buf,
+        r#"<w:style w:type="paragraph" w:styleId="TOCHeading"><w:name w:val="TOC Heading"/><w:basedOn w:val="Normal"/><w:pPr><w:spacing w:after="200"/></w:pPr><w:rPr><w:b/><w:color w:val="{:06X}"/><w:sz w:val="32"/></w:rPr></w:style>"#,
+        option.header_bg_color
+    )?;
+    for level in 1..=MAX_HEADING {
+        let size = 32usize.saturating_sub(level * 4).max(21);
+        writeln!(
+            buf,
+            r#"<w:style w:type="paragraph" w:styleId="Heading{level}"><w:name w:val="heading {level}"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:numPr><w:ilvl w:val="{ilvl}"/><w:numId w:val="1"/></w:numPr><w:spacing w:before="240" w:after="120"/><w:outlineLvl w:val="{ilvl}"/></w:pPr><w:rPr><w:b/><w:color w:val="{color:06X}"/><w:sz w:val="{size}"/></w:rPr></w:style>"#,
+            ilvl = level - 1,
+            color = option.header_bg_color,
+        )?;
+    }
+    writeln!(buf, "</w:styles>")?;
+    Ok(buf)
+}
+
+/// Outline numbering `1.`, `1.1.`, `1.1.1.` … for the heading styles.
+fn numbering() -> Result<String, std::fmt::Error> {
+    let mut buf = String::new();
+    writeln!(
+        buf,
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#
+    )?;
+    writeln!(buf, r#"<w:numbering xmlns:w="{W}">"#)?;
+    writeln!(
+        buf,
+        r#"<w:abstractNum w:abstractNumId="0"><w:multiLevelType w:val="multilevel"/>"#
+    )?;
+    for ilvl in 0..MAX_HEADING {
+        let text: String = (1..=ilvl + 1).map(|l| format!("%{l}.")).collect();
+        writeln!(
+            buf,
+            r#"<w:lvl w:ilvl="{ilvl}"><w:start w:val="1"/><w:numFmt w:val="decimal"/><w:pStyle w:val="Heading{}"/><w:lvlText w:val="{text}"/><w:lvlJc w:val="left"/><w:pPr><w:ind w:left="{indent}" w:hanging="{indent}"/></w:pPr></w:lvl>"#,
+            ilvl + 1,
+            indent = 425 + ilvl * 142,
+        )?;
+    }
+    writeln!(buf, "</w:abstractNum>")?;
+    writeln!(
+        buf,
+        r#"<w:num w:numId="1"><w:abstractNumId w:val="0"/></w:num>"#
+    )?;
+    writeln!(buf, "</w:numbering>")?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use zip::ZipArchive;
+
+    use super::*;
+
+    #[test]
+    fn headings_and_tables() {
+        let s = "
+title: Spec <title>
+
+cases:
+  - title: Login
+    children:
+      - title: Password
+        children:
+          - id: TC-1
+            title: Valid password
+            operations:
+              - Open the login page
+              - Enter the password
+            result:
+              status: pass
+";
+        let spec: TestSpec = s.parse().unwrap();
+        let bytes = generate_docx(&spec, &GenerateOption::default()).unwrap();
+
+        let mut zip = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut document = String::new();
+        zip.by_name("word/document.xml")
+            .unwrap()
+            .read_to_string(&mut document)
+            .unwrap();
+        assert!(document.contains(
+            r#"<w:pStyle w:val="Title"/></w:pPr><w:r><w:t xml:space="preserve">Spec &lt;title&gt;</w:t>"#
+        ));
+        assert!(document.contains(r#"TOC \o "1-2""#));
+        assert!(document.contains(
+            r#"<w:pStyle w:val="Heading2"/></w:pPr><w:r><w:t xml:space="preserve">Password</w:t>"#
+        ));
+        assert!(document.contains(r#"<w:gridSpan w:val="2"/><w:shd w:val="clear" w:color="auto" w:fill="5B9BD5"/></w:tcPr><w:p><w:r><w:rPr><w:b/><w:color w:val="FFFFFF"/></w:rPr><w:t xml:space="preserve">Valid password</w:t>"#));
+        assert!(document.contains(
+            r#"<w:t xml:space="preserve">1. Open the login page</w:t></w:r></w:p><w:p>"#
+        ));
+        assert!(document.contains(r#"<w:t xml:space="preserve">Pass</w:t>"#));
+        assert!(zip.by_name("word/numbering.xml").is_ok());
+    }
+}
@@ -1,9 +1,12 @@
 use crate::testspec::TestSpec;
 
 use mktemp::Temp;
-use xlsxwriter::{FormatAlignment, FormatBorder, FormatColor, Workbook, Worksheet, XlsxError};
+use xlsxwriter::{
+    Format, FormatAlignment, FormatBorder, FormatColor, Workbook, Worksheet, XlsxError,
+};
 
-use super::GenerateOption;
+use super::table::{case_text, rows, spans};
+use super::{sheet_name, Column, ColumnOption, GenerateOption};
 
 pub fn generate_excel(spec: &TestSpec, option: &GenerateOption) -> anyhow::Result<Vec<u8>> {
     let temp_file = Temp::new_file()?;
@@ -13,11 +16,13 @@ pub fn generate_excel(spec: &TestSpec, option: &GenerateOption) -> anyhow::Resul
     };
 
     let book = Workbook::new(filename);
-    let mut sheet = book.add_worksheet(Some(&spec.title))?;
+    let mut sheet = book.add_worksheet(Some(sheet_name(spec, option.locale)))?;
 
-    setup_columns(&mut sheet, option)?;
-    setup_header(&book, &mut sheet, option)?;
-    setup_body(&book, &mut sheet, &spec, option)?;
+    let columns = option.column_options.columns(spec)?;
+
+    setup_columns(&mut sheet, &columns)?;
+    setup_header(&book, &mut sheet, &columns, option)?;
+    setup_body(&book, &mut sheet, spec, &columns, option)?;
 
     book.close()?;
 
@@ -26,8 +31,11 @@ pub fn generate_excel(spec: &TestSpec, option: &GenerateOption) -> anyhow::Resul
     Ok(bytes)
 }
 
-fn setup_columns(sheet: &mut Worksheet, option: &GenerateOption) -> Result<(), XlsxError> {
-    for (i, o) in option.column_options.into_iter().enumerate() {
+fn setup_columns(
+    sheet: &mut Worksheet,
+    columns: &[(Column, &ColumnOption)],
+) -> Result<(), XlsxError> {
+    for (i, (_, o)) in columns.iter().enumerate() {
         sheet.set_column(i as _, i as _, o.width, None)?;
     }
 
@@ -37,12 +45,13 @@ fn setup_columns(sheet: &mut Worksheet, option: &GenerateOption) -> Result<(), X
 fn setup_header(
     book: &Workbook,
     sheet: &mut Worksheet,
+    columns: &[(Column, &ColumnOption)],
     option: &GenerateOption,
 ) -> Result<(), XlsxError> {
     const ROW: u32 = 0;
     let header_format = book
         .add_format()
-        .set_font_name(&option.font_family)
+        .set_font_name(option.font_family)
         .set_border(FormatBorder::Medium)
         .set_border_color(FormatColor::Custom(option.border_color))
         .set_text_wrap()
@@ -52,8 +61,8 @@ fn setup_header(
         .set_bold()
         .set_bg_color(FormatColor::Custom(option.header_bg_color));
 
-    for (i, o) in option.column_options.into_iter().enumerate() {
-        sheet.write_string(ROW, i as _, &o.header, Some(&header_format))?;
+    for (i, (_, o)) in columns.iter().enumerate() {
+        sheet.write_string(ROW, i as _, o.header, Some(&header_format))?;
     }
 
     Ok(())
@@ -63,11 +72,12 @@ fn setup_body(
     book: &Workbook,
     sheet: &mut Worksheet,
     spec: &TestSpec,
+    columns: &[(Column, &ColumnOption)],
     option: &GenerateOption,
 ) -> Result<(), XlsxError> {
     let center_align_format = book
         .add_format()
-        .set_font_name(&option.font_family)
+        .set_font_name(option.font_family)
         .set_text_wrap()
         .set_border(FormatBorder::Medium)
         .set_border_color(FormatColor::Custom(option.border_color))
@@ -76,121 +86,75 @@ fn setup_body(
 
     let left_align_format = book
         .add_format()
-        .set_font_name(&option.font_family)
+        .set_font_name(option.font_family)
         .set_text_wrap()
         .set_border(FormatBorder::Medium)
         .set_border_color(FormatColor::Custom(option.border_color))
         .set_align(FormatAlignment::Left)
         .set_align(FormatAlignment::VerticalTop);
 
-    let mut row = 1;
-    for primary in spec.cases.iter() {
-        let primary_start_row_no = row;
-
-        if primary.children.is_empty() {
-            sheet.write_number(row, 0, row as _, Some(&center_align_format))?;
-            sheet.write_blank(row, 1, Some(&center_align_format))?;
-            sheet.write_blank(row, 2, Some(&center_align_format))?;
-            sheet.write_blank(row, 3, Some(&center_align_format))?;
-            sheet.write_blank(row, 4, Some(&center_align_format))?;
-            sheet.write_blank(row, 5, Some(&center_align_format))?;
-            sheet.write_blank(row, 6, Some(&left_align_format))?;
-            sheet.write_blank(row, 7, Some(&left_align_format))?;
-            sheet.write_blank(row, 8, Some(&left_align_format))?;
-            row += 1;
-            continue;
-        }
+    const FIRST_ROW: u32 = 1;
+    let rows = rows(spec);
 
-        for secondary in primary.children.iter() {
-            let secondary_start_row_no = row;
-
-            if secondary.children.is_empty() {
-                sheet.write_number(row, 0, row as _, Some(&center_align_format))?;
-                sheet.write_string(row, 1, &primary.title, Some(&center_align_format))?;
-                sheet.write_blank(row, 2, Some(&center_align_format))?;
-                sheet.write_blank(row, 3, Some(&center_align_format))?;
-                sheet.write_blank(row, 4, Some(&center_align_format))?;
-                sheet.write_blank(row, 5, Some(&center_align_format))?;
-                sheet.write_blank(row, 6, Some(&left_align_format))?;
-                sheet.write_blank(row, 7, Some(&left_align_format))?;
-                sheet.write_blank(row, 8, Some(&left_align_format))?;
-                row += 1;
-                continue;
-            }
-
-            for tertiary in secondary.children.iter() {
-                let operations_string = tertiary
-                    .operations
-                    .iter()
-                    .enumerate()
-                    .map(|(i, o)| format!("{}. {o}", i + 1))
-                    .collect::<Vec<_>>()
-                    .join("\n");
-
-                let confirmations_string = tertiary
-                    .confirmations
-                    .iter()
-                    .map(|c| format!("- {c}"))
-                    .collect::<Vec<_>>()
-                    .join("\n");
-
-                let remarks_string = tertiary
-                    .remarks
-                    .iter()
-                    .map(|r| format!("- {r}"))
-                    .collect::<Vec<_>>()
-                    .join("\n");
-
-                sheet.write_number(row, 0, row as _, Some(&center_align_format))?;
-                sheet.write_string(row, 3, &tertiary.title, Some(&center_align_format))?;
-                sheet.write_blank(row, 4, Some(&center_align_format))?;
-                sheet.write_blank(row, 5, Some(&center_align_format))?;
-                sheet.write_string(row, 6, &operations_string, Some(&left_align_format))?;
-                sheet.write_string(row, 7, &confirmations_string, Some(&left_align_format))?;
-                sheet.write_string(row, 8, &remarks_string, Some(&left_align_format))?;
-
-                row += 1;
-            }
-
-            let secondary_end_row_no = row - 1;
-            if secondary_start_row_no == secondary_end_row_no {
-                sheet.write_string(
-                    secondary_start_row_no,
-                    2,
-                    &secondary.title,
-                    Some(&center_align_format),
-                )?;
+    for (i, r) in rows.iter().enumerate() {
+        let row = FIRST_ROW + i as u32;
+        for (col, (column, _)) in columns.iter().enumerate() {
+            let col = col as u16;
+            let format = if column.is_text() {
+                &left_align_format
             } else {
-                sheet.merge_range(
-                    secondary_start_row_no,
-                    2,
-                    secondary_end_row_no,
-                    2,
-                    &secondary.title,
-                    Some(&center_align_format),
-                )?;
+                &center_align_format
+            };
+            match (column, r.case()) {
+                (Column::No, _) => write_no(sheet, row, &r.item().id, format)?,
+                (Column::Item(_), _) => {}
+                (column, Some(case)) => match case_text(*column, case) {
+                    Some(text) if !text.is_empty() => {
+                        sheet.write_string(row, col, &text, Some(format))?
+                    }
+                    _ => sheet.write_blank(row, col, Some(format))?,
+                },
+                (_, None) => sheet.write_blank(row, col, Some(format))?,
             }
         }
+    }
 
-        let primary_end_row_no = row - 1;
-        if primary_start_row_no == primary_end_row_no {
-            sheet.write_string(
-                primary_start_row_no,
-                1,
-                &primary.title,
-                Some(&center_align_format),
-            )?;
-        } else {
-            sheet.merge_range(
-                primary_start_row_no,
-                1,
-                primary_end_row_no,
-                1,
-                &primary.title,
-                Some(&center_align_format),
-            )?;
+    for (col, (column, _)) in columns.iter().enumerate() {
+        let (col, level) = match column {
+            Column::Item(level) => (col as u16, *level),
+            _ => continue,
+        };
+        for span in spans(&rows, level) {
+            let first_row = FIRST_ROW + span.first as u32;
+            let last_row = FIRST_ROW + span.last as u32;
+            match span.item {
+                None => sheet.write_blank(first_row, col, Some(&center_align_format))?,
+                Some(item) if first_row == last_row => {
+                    sheet.write_string(first_row, col, &item.title, Some(&center_align_format))?
+                }
+                Some(item) => sheet.merge_range(
+                    first_row,
+                    col,
+                    last_row,
+                    col,
+                    &item.title,
+                    Some(&center_align_format),
+                )?,
+            }
         }
     }
 
     Ok(())
 }
+
+fn write_no(
+    sheet: &mut Worksheet,
+    row: u32,
+    id: &Option<String>,
+    format: &Format,
+) -> Result<(), XlsxError> {
+    match id {
+        Some(id) => sheet.write_string(row, 0, id, Some(format)),
+        None => sheet.write_number(row, 0, row as _, Some(format)),
+    }
+}
@@ -0,0 +1,261 @@
+use std::fmt::Write;
+
+use anyhow::ensure;
+use regex::Regex;
+
+use crate::testspec::{Item, TestSpec};
+
+/// Generates Gherkin: the primary items become `Feature`s, the secondary items `Rule`s and
+/// the test cases `Scenario`s, with the preconditions as `Given`, the operations as `When`
+/// and the confirmations as `Then` steps. With fewer levels, the spec title is the feature
+/// (depth 1) or there are no rules (depth 2).
+///
+/// Cases with parameters become a `Scenario Outline` with `<name>` placeholders and an
+/// `Examples` table of every combination.
+pub fn generate_gherkin(spec: &TestSpec) -> anyhow::Result<String> {
+    ensure!(
+        spec.depth <= 3,
+        "gherkin has no keyword for level 4 and below (depth {})",
+        spec.depth
+    );
+
+    let mut buf = String::new();
+    if spec.depth == 1 {
+        writeln!(buf, "Feature: {}", spec.title)?;
+        write_scenarios(&mut buf, &spec.cases, 1)?;
+        return Ok(buf);
+    }
+
+    for (i, feature) in spec.cases.iter().enumerate() {
+        if i > 0 {
+            writeln!(buf)?;
+        }
+        write_tags(&mut buf, feature, 0)?;
+        writeln!(buf, "Feature: {}", feature.title)?;
+        if spec.depth == 2 {
+            write_scenarios(&mut buf, &feature.children, 1)?;
+            continue;
+        }
+        for rule in &feature.children {
+            writeln!(buf)?;
+            write_tags(&mut buf, rule, 1)?;
+            writeln!(buf, "  Rule: {}", rule.title)?;
+            write_scenarios(&mut buf, &rule.children, 2)?;
+        }
+    }
+    Ok(buf)
+}
+
+fn write_scenarios(buf: &mut String, cases: &[Item], indent: usize) -> anyhow::Result<()> {
+    let pad = "  ".repeat(indent);
+    for case in cases {
+        writeln!(buf)?;
+        write_tags(buf, case, indent)?;
+
+        let outline = !case.parameters.is_empty() || !case.examples.is_empty();
+        let keyword = if outline {
+            "Scenario Outline"
+        } else {
+            "Scenario"
+        };
+        let text = |s: &str| {
+            if outline {
+                placeholders(s)
+            } else {
+                s.to_string()
+            }
+        };
+        writeln!(buf, "{pad}{keyword}: {}", text(&case.title))?;
+
+        for (label, values) in [
+            ("Environment", &case.environment),
+            ("Test data", &case.test_data),
+        ] {
+            for value in values {
+                writeln!(buf, "{pad}  {label}: {}", text(value))?;
+            }
+        }
+        for remark in &case.remarks {
+            writeln!(buf, "{pad}  {}", text(remark))?;
+        }
+
+        let mut steps = Steps::new(buf, &pad);
+        for precondition in &case.preconditions {
+            steps.write("Given", &text(precondition))?;
+        }
+        for operation in &case.operations {
+            steps.write("When", &text(operation))?;
+        }
+        for confirmation in &case.confirmations {
+            steps.write("Then", &text(confirmation))?;
+        }
+        for step in &case.steps {
+            steps.write("When", &text(&step.action))?;
+            if let Some(data) = &step.data {
+                steps.doc_string(&text(data))?;
+            }
+            if let Some(expected) = &step.expected {
+                steps.write("Then", &text(expected))?;
+            }
+        }
+
+        if outline {
+            write_examples(buf, case, indent + 1)?;
+        }
+    }
+    Ok(())
+}
+
+/// Tags of `item`, with the ID and priority as `@id:` and `@priority:` tags.
+fn write_tags(buf: &mut String, item: &Item, indent: usize) -> std::fmt::Result {
+    let mut tags = Vec::new();
+    if let Some(id) = &item.id {
+        tags.push(format!("@id:{id}"));
+    }
+    if let Some(priority) = item.priority {
+        tags.push(format!("@priority:{priority}"));
+    }
+    tags.extend(item.tags.iter().map(|t| format!("@{t}")));
+    if tags.is_empty() {
+        return Ok(());
+    }
+    writeln!(buf, "{}{}", "  ".repeat(indent), tags.join(" "))
+}
+
+fn write_examples(buf: &mut String, case: &Item, indent: usize) -> anyhow::Result<()> {
+    let bindings = case.bindings()?;
+    let names: Vec<_> = match bindings.first() {
+        Some(binding) => binding.keys().collect(),
+        None => return Ok(()),
+    };
+    let pad = "  ".repeat(indent);
+    let row = |cells: Vec<&str>| {
+        let cells: Vec<_> = cells
+            .iter()
+            .map(|c| c.replace('\\', "\\\\").replace('|', "\\|"))
+            .collect();
+        format!("{pad}  | {} |", cells.join(" | "))
+    };
+
+    writeln!(buf)?;
+    writeln!(buf, "{pad}Examples:")?;
+    writeln!(buf, "{}", row(names.iter().map(|n| &n[..]).collect()))?;
+    for binding in &bindings {
+        let cells = names
+            .iter()
+            .map(|n| binding.get(*n).map_or("", |v| &v[..]))
+            .collect();
+        writeln!(buf, "{}", row(cells))?;
+    }
+    Ok(())
+}
+
+/// Replaces `{{name}}` with the Gherkin placeholder `<name>`.
+fn placeholders(s: &str) -> String {
+    let regex = match Regex::new(r"\{\{\s*([^{}]*?)\s*\}\}") {
+        Ok(regex) => regex,
+        Err(_) => unreachable!(),
+    };
+    regex.replace_all(s, "<$1>").into_owned()
+}
+
+/// Writes steps, using `And` when a step has the same keyword as the previous one.
+struct Steps<'a> {
+    buf: &'a mut String,
+    pad: String,
+    last: Option<&'static str>,
+}
+
+impl<'a> Steps<'a> {
+    fn new(buf: &'a mut String, pad: &str) -> Self {
+        Steps {
+            buf,
+            pad: format!("{pad}  "),
+            last: None,
+        }
+    }
+
+    fn write(&mut self, keyword: &'static str, text: &str) -> std::fmt::Result {
+        let shown = if self.last == Some(keyword) {
+            "And"
+        } else {
+            keyword
+        };
+        self.last = Some(keyword);
+        writeln!(self.buf, "{}{shown} {text}", self.pad)
+    }
+
+    fn doc_string(&mut self, text: &str) -> std::fmt::Result {
+        let pad = format!("{}  ", self.pad);
+        writeln!(self.buf, "{pad}\"\"\"")?;
+        for line in text.lines() {
+            writeln!(self.buf, "{pad}{line}")?;
+        }
+        writeln!(self.buf, "{pad}\"\"\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn features_rules_and_scenarios() {
+        let s = "
+title: Spec title
+
+cases:
+  - title: Login
+    tags: [auth]
+    children:
+      - title: Password
+        children:
+          - id: TC-1
+            title: Valid password
+            tags: [smoke]
+            remarks:
+              - Use a test account
+            preconditions:
+              - The user is registered
+            operations:
+              - Open the login page
+              - Enter the password
+            confirmations:
+              - The top page is shown
+          - title: Log in as {{role}}
+            parameters:
+              role: [admin, guest]
+            steps:
+              - action: Enter the {{ role }} password
+                data: hunter2
+                expected: The top page is shown
+";
+        let spec: TestSpec = s.parse().unwrap();
+        let expected = r#"@auth
+Feature: Login
+
+  Rule: Password
+
+    @id:TC-1 @smoke
+    Scenario: Valid password
+      Use a test account
+      Given The user is registered
+      When Open the login page
+      And Enter the password
+      Then The top page is shown
+
+    Scenario Outline: Log in as <role>
+      When Enter the <role> password
+        """
+        hunter2
+        """
+      Then The top page is shown
+
+      Examples:
+        | role |
+        | admin |
+        | guest |
+"#;
+        assert_eq!(expected, generate_gherkin(&spec).unwrap());
+    }
+}
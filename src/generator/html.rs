@@ -0,0 +1,213 @@
+use std::fmt::Write;
+
+use crate::testspec::{Item, Status, TestSpec};
+
+use super::table::case_text;
+use super::{escape, Column, ColumnOption, GenerateOption};
+
+/// Generates a single self-contained HTML page: the items above the test cases form a
+/// collapsible tree, and the test cases of each item are listed in a table with the Excel
+/// columns.
+pub fn generate_html(spec: &TestSpec, option: &GenerateOption) -> anyhow::Result<String> {
+    let columns: Vec<_> = option
+        .column_options
+        .columns(spec)?
+        .into_iter()
+        .filter(|(c, _)| !matches!(c, Column::Item(level) if level + 1 < spec.depth))
+        .collect();
+
+    let mut buf = String::new();
+    writeln!(buf, "<!DOCTYPE html>")?;
+    writeln!(buf, "<html>")?;
+    writeln!(buf, "<head>")?;
+    writeln!(buf, "<meta charset=\"utf-8\">")?;
+    writeln!(buf, "<title>{}</title>", escape(&spec.title))?;
+    writeln!(buf, "<style>\n{}</style>", style(option))?;
+    writeln!(buf, "</head>")?;
+    writeln!(buf, "<body>")?;
+    writeln!(buf, "<h1>{}</h1>", escape(&spec.title))?;
+
+    let mut writer = Writer {
+        buf: &mut buf,
+        columns: &columns,
+        depth: spec.depth,
+        row: 0,
+    };
+    writer.write_items(&spec.cases, 1)?;
+
+    writeln!(buf, "<script>")?;
+    writeln!(
+        buf,
+        "window.addEventListener('beforeprint', () => document.querySelectorAll('details').forEach(d => d.open = true));"
+    )?;
+    writeln!(buf, "</script>")?;
+    writeln!(buf, "</body>")?;
+    writeln!(buf, "</html>")?;
+    Ok(buf)
+}
+
+fn style(option: &GenerateOption) -> String {
+    format!(
+        "body {{ font-family: \"{font}\", sans-serif; color: #{body_font:06x}; background: #{body_bg:06x}; }}
+details {{ margin: 0.25em 0 0.25em 1em; }}
+summary {{ font-weight: bold; cursor: pointer; }}
+table {{ border-collapse: collapse; margin: 0.5em 0 1em; }}
+th, td {{ border: 2px solid #{border:06x}; padding: 0.25em 0.5em; text-align: center; vertical-align: middle; white-space: pre-wrap; }}
+th {{ background: #{header_bg:06x}; color: #{header_font:06x}; }}
+td.text {{ text-align: left; vertical-align: top; }}
+.badge {{ display: inline-block; padding: 0 0.5em; border-radius: 0.75em; color: #ffffff; }}
+.badge.pass {{ background: #2e7d32; }}
+.badge.fail {{ background: #c62828; }}
+.badge.blocked {{ background: #ef6c00; }}
+.badge.skip {{ background: #757575; }}
+@media print {{
+  summary {{ list-style: none; }}
+  table {{ page-break-inside: auto; }}
+  tr {{ page-break-inside: avoid; }}
+  .badge {{ -webkit-print-color-adjust: exact; print-color-adjust: exact; }}
+}}
+",
+        font = option.font_family,
+        body_font = option.body_font_color,
+        body_bg = option.body_bg_color,
+        border = option.border_color,
+        header_bg = option.header_bg_color,
+        header_font = option.header_font_color,
+    )
+}
+
+struct Writer<'a, 'b> {
+    buf: &'a mut String,
+    columns: &'a [(Column, &'a ColumnOption<'b>)],
+    depth: usize,
+    /// Row number of the last test case, counted like the Excel rows.
+    row: usize,
+}
+
+impl Writer<'_, '_> {
+    fn write_items(&mut self, items: &[Item], level: usize) -> std::fmt::Result {
+        if level == self.depth {
+            return self.write_table(items);
+        }
+
+        for item in items {
+            writeln!(self.buf, "<details open>")?;
+            writeln!(self.buf, "<summary>{}</summary>", escape(&heading(item)))?;
+            if item.children.is_empty() {
+                self.row += 1;
+            }
+            self.write_items(&item.children, level + 1)?;
+            writeln!(self.buf, "</details>")?;
+        }
+        Ok(())
+    }
+
+    fn write_table(&mut self, cases: &[Item]) -> std::fmt::Result {
+        if cases.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(self.buf, "<table>")?;
+        writeln!(self.buf, "<colgroup>")?;
+        for (_, o) in self.columns {
+            writeln!(self.buf, "<col style=\"width: {}ch\">", o.width)?;
+        }
+        writeln!(self.buf, "</colgroup>")?;
+        let headers: String = self
+            .columns
+            .iter()
+            .map(|(_, o)| format!("<th>{}</th>", escape(o.header)))
+            .collect();
+        writeln!(self.buf, "<tr>{headers}</tr>")?;
+
+        for case in cases {
+            self.row += 1;
+            write!(self.buf, "<tr>")?;
+            for (column, _) in self.columns {
+                match column {
+                    Column::No => {
+                        let no = match &case.id {
+                            Some(id) => id.clone(),
+                            None => self.row.to_string(),
+                        };
+                        write!(self.buf, "<td>{}</td>", escape(&no))?;
+                    }
+                    Column::Item(_) => write!(self.buf, "<td>{}</td>", escape(&case.title))?,
+                    Column::Result => match &case.result {
+                        Some(result) => write!(
+                            self.buf,
+                            "<td><span class=\"badge {}\">{}</span></td>",
+                            status_class(result.status),
+                            result.status
+                        )?,
+                        None => write!(self.buf, "<td></td>")?,
+                    },
+                    column => {
+                        let text = case_text(*column, case).unwrap_or_default();
+                        let class = if column.is_text() {
+                            " class=\"text\""
+                        } else {
+                            ""
+                        };
+                        write!(self.buf, "<td{class}>{}</td>", escape(&text))?;
+                    }
+                }
+            }
+            writeln!(self.buf, "</tr>")?;
+        }
+
+        writeln!(self.buf, "</table>")
+    }
+}
+
+fn heading(item: &Item) -> String {
+    match &item.id {
+        Some(id) => format!("[{id}] {}", item.title),
+        None => item.title.clone(),
+    }
+}
+
+fn status_class(status: Status) -> &'static str {
+    match status {
+        Status::Pass => "pass",
+        Status::Fail => "fail",
+        Status::Blocked => "blocked",
+        Status::Skip => "skip",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_and_table() {
+        let s = "
+title: Spec <title>
+depth: 2
+
+cases:
+  - title: Login
+    children:
+      - id: TC-1
+        title: Valid password
+        operations:
+          - Enter \"a&b\"
+        result:
+          status: blocked
+      - title: Invalid password
+  - title: Payment
+";
+        let spec: TestSpec = s.parse().unwrap();
+        let html = generate_html(&spec, &GenerateOption::default()).unwrap();
+
+        assert!(html.contains("<title>Spec &lt;title&gt;</title>"));
+        assert!(html.contains("<summary>Login</summary>"));
+        assert!(html.contains("<th>No.</th><th>Secondary Item</th><th>Operator</th>"));
+        assert!(html.contains(
+            "<tr><td>TC-1</td><td>Valid password</td><td></td><td><span class=\"badge blocked\">Blocked</span></td><td class=\"text\">1. Enter &quot;a&amp;b&quot;</td>"
+        ));
+        assert!(html.contains("<tr><td>2</td><td>Invalid password</td>"));
+        assert!(html.contains("<summary>Payment</summary>\n</details>"));
+    }
+}
@@ -0,0 +1,307 @@
+use std::fmt::Write;
+
+use crate::testspec::{Item, Status, TestSpec};
+
+use super::escape;
+use super::table::{bullets_text, confirmations_text, operations_text, parameters_text};
+
+/// Generates JUnit XML: the spec is the `<testsuites>` root, the items above the test cases
+/// are nested `<testsuite>`s, and every test case is a `<testcase>`. Failed, blocked and
+/// skipped results become `<failure>` and `<skipped>`, so that manual runs can be shown next
+/// to automated ones.
+pub fn generate_junit(spec: &TestSpec) -> anyhow::Result<String> {
+    let mut buf = String::new();
+    writeln!(buf, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    let count = Count::of(&spec.cases, 1, spec.depth);
+    writeln!(buf, "<testsuites name=\"{}\"{count}>", escape(&spec.title))?;
+
+    let mut writer = Writer {
+        buf: &mut buf,
+        depth: spec.depth,
+        ancestors: Vec::new(),
+    };
+    if spec.depth == 1 {
+        // Test cases can't be direct children of <testsuites>.
+        writer.write_suite(&spec.title, &spec.cases, 1)?;
+    } else {
+        writer.write_items(&spec.cases, 1)?;
+    }
+
+    writeln!(buf, "</testsuites>")?;
+    Ok(buf)
+}
+
+#[derive(Debug, Default)]
+struct Count {
+    tests: usize,
+    failures: usize,
+    skipped: usize,
+}
+
+impl Count {
+    fn of(items: &[Item], level: usize, depth: usize) -> Count {
+        let mut count = Count::default();
+        for item in items {
+            if level < depth {
+                let c = Count::of(&item.children, level + 1, depth);
+                count.tests += c.tests;
+                count.failures += c.failures;
+                count.skipped += c.skipped;
+                continue;
+            }
+            count.tests += 1;
+            match item.result.as_ref().map(|r| r.status) {
+                Some(Status::Fail) => count.failures += 1,
+                Some(Status::Blocked | Status::Skip) => count.skipped += 1,
+                Some(Status::Pass) | None => {}
+            }
+        }
+        count
+    }
+}
+
+impl std::fmt::Display for Count {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            " tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\"",
+            self.tests, self.failures, self.skipped
+        )
+    }
+}
+
+struct Writer<'a, 'b> {
+    buf: &'a mut String,
+    depth: usize,
+    /// The items above the current one, whose titles make up the test case class name.
+    ancestors: Vec<&'b Item>,
+}
+
+impl<'b> Writer<'_, 'b> {
+    fn write_items(&mut self, items: &'b [Item], level: usize) -> std::fmt::Result {
+        for item in items {
+            if level == self.depth {
+                self.write_case(item)?;
+            } else {
+                self.ancestors.push(item);
+                self.write_suite(&item.title, &item.children, level + 1)?;
+                self.ancestors.pop();
+            }
+        }
+        Ok(())
+    }
+
+    fn write_suite(&mut self, name: &str, items: &'b [Item], level: usize) -> std::fmt::Result {
+        let count = Count::of(items, level, self.depth);
+        writeln!(self.buf, "<testsuite name=\"{}\"{count}>", escape(name))?;
+        self.write_items(items, level)?;
+        writeln!(self.buf, "</testsuite>")
+    }
+
+    fn write_case(&mut self, case: &Item) -> std::fmt::Result {
+        writeln!(
+            self.buf,
+            "<testcase name=\"{}\" classname=\"{}\">",
+            escape(&case.title),
+            escape(&self.class_name())
+        )?;
+
+        let properties = properties(case, &self.ancestors);
+        if !properties.is_empty() {
+            writeln!(self.buf, "<properties>")?;
+            for (name, value) in properties {
+                writeln!(
+                    self.buf,
+                    "<property name=\"{name}\" value=\"{}\"/>",
+                    escape(&value)
+                )?;
+            }
+            writeln!(self.buf, "</properties>")?;
+        }
+
+        if let Some(result) = &case.result {
+            let message = match (&result.note, result.status) {
+                (Some(note), _) => note.clone(),
+                (None, status) => status.to_string(),
+            };
+            match result.status {
+                Status::Pass => {}
+                Status::Fail => writeln!(self.buf, "<failure message=\"{}\"/>", escape(&message))?,
+                Status::Blocked | Status::Skip => {
+                    writeln!(self.buf, "<skipped message=\"{}\"/>", escape(&message))?
+                }
+            }
+        }
+
+        let out = system_out(case);
+        if !out.is_empty() {
+            writeln!(self.buf, "<system-out>{}</system-out>", escape(&out))?;
+        }
+
+        writeln!(self.buf, "</testcase>")
+    }
+
+    fn class_name(&self) -> String {
+        let titles: Vec<&str> = self.ancestors.iter().map(|a| &a.title[..]).collect();
+        titles.join(".")
+    }
+}
+
+/// The ID, tags, priority and result of `case`. Like `--filter`, the tags include those of
+/// the `ancestors`, and the priority falls back to the nearest ancestor's.
+fn properties(case: &Item, ancestors: &[&Item]) -> Vec<(&'static str, String)> {
+    let mut properties = Vec::new();
+    if let Some(id) = &case.id {
+        properties.push(("id", id.clone()));
+    }
+    let mut tags: Vec<&str> = Vec::new();
+    for tag in ancestors
+        .iter()
+        .copied()
+        .chain([case])
+        .flat_map(|i| &i.tags)
+    {
+        if !tags.contains(&&tag[..]) {
+            tags.push(tag);
+        }
+    }
+    if !tags.is_empty() {
+        properties.push(("tags", tags.join(", ")));
+    }
+    let priority = case
+        .priority
+        .or_else(|| ancestors.iter().rev().find_map(|i| i.priority));
+    if let Some(priority) = priority {
+        properties.push(("priority", priority.to_string()));
+    }
+    if let Some(result) = &case.result {
+        properties.push(("status", result.status.to_string()));
+        for (name, value) in [
+            ("operator", &result.operator),
+            ("date", &result.date),
+            ("defect", &result.defect),
+        ] {
+            if let Some(value) = value {
+                properties.push((name, value.clone()));
+            }
+        }
+    }
+    properties
+}
+
+/// The case fields as plain text sections, e.g. `Operations:` followed by the numbered
+/// operations.
+fn system_out(case: &Item) -> String {
+    [
+        ("Environment", bullets_text(&case.environment)),
+        ("Preconditions", bullets_text(&case.preconditions)),
+        ("Test data", bullets_text(&case.test_data)),
+        ("Parameters", parameters_text(case)),
+        ("Operations", operations_text(case)),
+        ("Confirmations", confirmations_text(case)),
+        ("Remarks", bullets_text(&case.remarks)),
+    ]
+    .into_iter()
+    .filter(|(_, text)| !text.is_empty())
+    .map(|(label, text)| format!("{label}:\n{text}"))
+    .collect::<Vec<_>>()
+    .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suites_and_results() {
+        let s = "
+title: Spec title
+depth: 2
+
+cases:
+  - title: Login
+    children:
+      - id: TC-1
+        title: Valid password
+        operations:
+          - Enter <password>
+        confirmations:
+          - The top page is shown
+        result:
+          status: fail
+          note: Error 500
+          defect: BUG-1
+      - title: Invalid password
+        result:
+          status: blocked
+      - title: Logout
+  - title: Payment
+";
+        let spec: TestSpec = s.parse().unwrap();
+        let xml = generate_junit(&spec).unwrap();
+        let expected = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="Spec title" tests="3" failures="1" errors="0" skipped="1">
+<testsuite name="Login" tests="3" failures="1" errors="0" skipped="1">
+<testcase name="Valid password" classname="Login">
+<properties>
+<property name="id" value="TC-1"/>
+<property name="status" value="Fail"/>
+<property name="defect" value="BUG-1"/>
+</properties>
+<failure message="Error 500"/>
+<system-out>Operations:
+1. Enter &lt;password&gt;
+
+Confirmations:
+- The top page is shown</system-out>
+</testcase>
+<testcase name="Invalid password" classname="Login">
+<properties>
+<property name="status" value="Blocked"/>
+</properties>
+<skipped message="Blocked"/>
+</testcase>
+<testcase name="Logout" classname="Login">
+</testcase>
+</testsuite>
+<testsuite name="Payment" tests="0" failures="0" errors="0" skipped="0">
+</testsuite>
+</testsuites>
+"#;
+        assert_eq!(expected, xml);
+    }
+
+    #[test]
+    fn inherited_properties() {
+        let s = "
+title: Spec title
+depth: 2
+
+cases:
+  - title: Login
+    tags: [auth]
+    priority: 2
+    children:
+      - title: Valid password
+        tags: [smoke, auth]
+      - title: Invalid password
+        priority: 1
+";
+        let spec: TestSpec = s.parse().unwrap();
+        let xml = generate_junit(&spec).unwrap();
+        assert!(xml.contains(
+            r#"<testcase name="Valid password" classname="Login">
+<properties>
+<property name="tags" value="auth, smoke"/>
+<property name="priority" value="2"/>
+</properties>"#
+        ));
+        assert!(xml.contains(
+            r#"<testcase name="Invalid password" classname="Login">
+<properties>
+<property name="tags" value="auth"/>
+<property name="priority" value="1"/>
+</properties>"#
+        ));
+    }
+}
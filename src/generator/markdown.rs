@@ -1,68 +1,163 @@
-use std::fmt::Write;
-
-use crate::testspec::{PrimaryItem, SecondaryItem, TertiaryItem, TestSpec};
-
-pub fn generate_markdown(spec: &TestSpec) -> Result<String, std::fmt::Error> {
-    let TestSpec { title, cases } = &spec;
-    let mut buf = String::new();
-    writeln!(&mut buf, "# {title}")?;
-
-    for PrimaryItem {
-        title,
-        children: secondaries,
-    } in cases
-    {
-        writeln!(&mut buf)?;
-        writeln!(&mut buf, "## {title}")?;
-
-        for SecondaryItem {
-            title,
-            children: tertiaries,
-        } in secondaries
-        {
-            writeln!(&mut buf)?;
-            writeln!(&mut buf, "### {title}")?;
-
-            for TertiaryItem {
-                title,
-                operations,
-                confirmations,
-                remarks,
-            } in tertiaries
-            {
-                writeln!(&mut buf)?;
-                writeln!(&mut buf, "#### {title}")?;
-
-                for (i, operation) in operations.iter().enumerate() {
-                    if i == 0 {
-                        writeln!(&mut buf)?;
-                        writeln!(&mut buf, "##### Operations")?;
-                        writeln!(&mut buf)?;
-                    }
-                    let order = i + 1;
-                    writeln!(&mut buf, "{order}. {operation}")?;
-                }
-
-                for (i, confirmation) in confirmations.iter().enumerate() {
-                    if i == 0 {
-                        writeln!(&mut buf)?;
-                        writeln!(&mut buf, "##### Confirmation")?;
-                        writeln!(&mut buf)?;
-                    }
-                    writeln!(&mut buf, "- [ ] {confirmation}")?;
-                }
-
-                for (i, remark) in remarks.iter().enumerate() {
-                    if i == 0 {
-                        writeln!(&mut buf)?;
-                        writeln!(&mut buf, "##### Remarks")?;
-                        writeln!(&mut buf)?;
-                    }
-                    writeln!(&mut buf, "- {remark}")?;
-                }
-            }
-        }
+use anyhow::ensure;
+
+use crate::testspec::TestSpec;
+
+use super::{generate_template, GenerateOption};
+
+const TEMPLATE: &str = include_str!("../../templates/markdown.md.tera");
+
+/// Generates Markdown with the built-in template, `templates/markdown.md.tera`. Each level
+/// takes one heading level below the title and the case sections the next one, so the depth
+/// is limited to 4.
+pub fn generate_markdown(spec: &TestSpec, option: &GenerateOption) -> anyhow::Result<String> {
+    ensure!(
+        spec.depth <= 4,
+        "markdown has no heading level for the sections of level 5 cases and below (depth {})",
+        spec.depth
+    );
+    generate_template(spec, option, "markdown.md", TEMPLATE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_table() {
+        let s = "
+title: Spec title
+depth: 1
+
+cases:
+  - title: Case 1
+    steps:
+      - action: Enter `a|b`
+        expected: |-
+          Accepted
+          without errors
+      - action: Press the login button
+";
+        let spec: TestSpec = s.parse().unwrap();
+        let expected = "# Spec title
+
+## Case 1
+
+### Steps
+
+| # | Action | Expected |
+| --- | --- | --- |
+| 1 | Enter `a\\|b` | Accepted<br>without errors |
+| 2 | Press the login button |  |
+";
+        assert_eq!(
+            expected,
+            generate_markdown(&spec, &GenerateOption::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn sections() {
+        let s = "
+title: Spec title
+depth: 2
+
+cases:
+  - title: Login
+    environment:
+      - Chrome
+    children:
+      - id: TC-1
+        title: Log in as {{role}}
+        parameters:
+          role: [admin]
+        examples:
+          - user: a|b
+          - password: secret
+        operations:
+          - Open the login page
+        confirmations:
+          - The top page is shown
+        result:
+          status: pass
+          operator: Alice
+      - title: Reset the password
+        steps:
+          - action: Enter the e-mail address
+            data: alice@example.com
+          - action: Press the button
+            expected: |-
+              A mail is sent
+              to the address
+";
+        let spec: TestSpec = s.parse().unwrap();
+        let expected = "# Spec title
+
+## Login
+
+### Environment
+
+- Chrome
+
+### [TC-1] Log in as {{role}}
+
+#### Parameters
+
+- role: admin
+
+#### Examples
+
+| password | user |
+| --- | --- |
+|  | a\\|b |
+| secret |  |
+
+#### Operations
+
+1. Open the login page
+
+#### Confirmations
+
+- [x] The top page is shown
+
+#### Result
+
+- Status: Pass
+- Operator: Alice
+
+### Reset the password
+
+#### Steps
+
+| # | Action | Expected | Data |
+| --- | --- | --- | --- |
+| 1 | Enter the e-mail address |  | alice@example.com |
+| 2 | Press the button | A mail is sent<br>to the address |  |
+";
+        assert_eq!(
+            expected,
+            generate_markdown(&spec, &GenerateOption::default()).unwrap()
+        );
     }
 
-    Ok(buf)
+    #[test]
+    fn too_deep() {
+        let spec: TestSpec = "
+title: Spec title
+depth: 5
+cases:
+  - title: A
+    children:
+      - title: B
+        children:
+          - title: C
+            children:
+              - title: D
+                children:
+                  - title: Case
+"
+        .parse()
+        .unwrap();
+        let err = generate_markdown(&spec, &GenerateOption::default()).unwrap_err();
+        assert!(err.to_string().contains("depth 5"), "{err}");
+    }
 }
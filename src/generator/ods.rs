@@ -0,0 +1,239 @@
+use std::fmt::Write as _;
+use std::io::{Cursor, Write as _};
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::testspec::{Item, TestSpec};
+
+use super::table::{case_text, rows, spans};
+use super::{escape, sheet_name, Column, ColumnOption, GenerateOption};
+
+const MIMETYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+ <manifest:file-entry manifest:full-path="/" manifest:version="1.2" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/>
+ <manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>
+"#;
+
+/// Generates an OpenDocument spreadsheet in the layout of `generate_excel`.
+pub fn generate_ods(spec: &TestSpec, option: &GenerateOption) -> anyhow::Result<Vec<u8>> {
+    let content = content(spec, option)?;
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    // The mimetype must come first and uncompressed so the file type can be sniffed.
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(MIMETYPE.as_bytes())?;
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file("META-INF/manifest.xml", deflated)?;
+    zip.write_all(MANIFEST.as_bytes())?;
+    zip.start_file("content.xml", deflated)?;
+    zip.write_all(content.as_bytes())?;
+
+    Ok(zip.finish()?.into_inner())
+}
+
+/// A cell of an item column: the first row of a merged range, or a row it covers.
+#[derive(Debug, Clone, Copy)]
+enum ItemCell<'a> {
+    Start { item: Option<&'a Item>, rows: usize },
+    Covered,
+}
+
+fn content(spec: &TestSpec, option: &GenerateOption) -> anyhow::Result<String> {
+    let columns = option.column_options.columns(spec)?;
+    let rows = rows(spec);
+
+    // The cells of each item column, by level and row.
+    let item_cells: Vec<_> = (0..spec.depth)
+        .map(|level| {
+            let mut cells = vec![ItemCell::Covered; rows.len()];
+            for span in spans(&rows, level) {
+                cells[span.first] = ItemCell::Start {
+                    item: span.item,
+                    rows: span.last - span.first + 1,
+                };
+            }
+            cells
+        })
+        .collect();
+
+    let mut buf = String::new();
+    writeln!(buf, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        buf,
+        r#"<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" office:version="1.2">"#
+    )?;
+    write_styles(&mut buf, &columns, option)?;
+    writeln!(buf, "<office:body>")?;
+    writeln!(buf, "<office:spreadsheet>")?;
+    writeln!(
+        buf,
+        r#"<table:table table:name="{}">"#,
+        escape(sheet_name(spec, option.locale))
+    )?;
+    for i in 0..columns.len() {
+        writeln!(buf, r#"<table:table-column table:style-name="co{i}"/>"#)?;
+    }
+
+    write!(buf, "<table:table-row>")?;
+    for (_, o) in &columns {
+        write_text_cell(&mut buf, "header", o.header, 1)?;
+    }
+    writeln!(buf, "</table:table-row>")?;
+
+    for (i, row) in rows.iter().enumerate() {
+        write!(buf, "<table:table-row>")?;
+        for (column, _) in &columns {
+            let style = if column.is_text() { "left" } else { "center" };
+            match (column, row.case()) {
+                (Column::No, _) => match &row.item().id {
+                    Some(id) => write_text_cell(&mut buf, style, id, 1)?,
+                    None => write!(
+                        buf,
+                        r#"<table:table-cell table:style-name="{style}" office:value-type="float" office:value="{no}"><text:p>{no}</text:p></table:table-cell>"#,
+                        no = i + 1
+                    )?,
+                },
+                (Column::Item(level), _) => match item_cells[*level][i] {
+                    ItemCell::Start { item, rows } => {
+                        let title = item.map_or("", |item| &item.title[..]);
+                        write_text_cell(&mut buf, style, title, rows)?;
+                    }
+                    ItemCell::Covered => write!(buf, "<table:covered-table-cell/>")?,
+                },
+                (column, Some(case)) => {
+                    let text = case_text(*column, case).unwrap_or_default();
+                    write_text_cell(&mut buf, style, &text, 1)?;
+                }
+                (_, None) => write_text_cell(&mut buf, style, "", 1)?,
+            }
+        }
+        writeln!(buf, "</table:table-row>")?;
+    }
+
+    writeln!(buf, "</table:table>")?;
+    writeln!(buf, "</office:spreadsheet>")?;
+    writeln!(buf, "</office:body>")?;
+    writeln!(buf, "</office:document-content>")?;
+    Ok(buf)
+}
+
+/// Column widths and the header, centered and left-aligned cell styles.
+fn write_styles(
+    buf: &mut String,
+    columns: &[(Column, &ColumnOption)],
+    option: &GenerateOption,
+) -> std::fmt::Result {
+    writeln!(buf, "<office:automatic-styles>")?;
+    for (i, (_, o)) in columns.iter().enumerate() {
+        // Excel widths count characters of the default font, about 7px each plus padding.
+        let inches = (o.width * 7.0 + 5.0) / 96.0;
+        writeln!(
+            buf,
+            r#"<style:style style:name="co{i}" style:family="table-column"><style:table-column-properties style:column-width="{inches:.3}in"/></style:style>"#
+        )?;
+    }
+
+    for (name, align, valign, font, bg, weight) in [
+        (
+            "header",
+            "center",
+            "middle",
+            option.header_font_color,
+            option.header_bg_color,
+            "bold",
+        ),
+        (
+            "center",
+            "center",
+            "middle",
+            option.body_font_color,
+            option.body_bg_color,
+            "normal",
+        ),
+        (
+            "left",
+            "start",
+            "top",
+            option.body_font_color,
+            option.body_bg_color,
+            "normal",
+        ),
+    ] {
+        writeln!(
+            buf,
+            r##"<style:style style:name="{name}" style:family="table-cell"><style:table-cell-properties fo:background-color="#{bg:06x}" fo:border="1.5pt solid #{border:06x}" fo:wrap-option="wrap" style:vertical-align="{valign}"/><style:paragraph-properties fo:text-align="{align}"/><style:text-properties fo:font-family="{font_family}" fo:color="#{font:06x}" fo:font-weight="{weight}"/></style:style>"##,
+            border = option.border_color,
+            font_family = escape(option.font_family),
+        )?;
+    }
+    writeln!(buf, "</office:automatic-styles>")
+}
+
+/// Writes a string cell spanning `rows` rows, with one paragraph per line.
+fn write_text_cell(buf: &mut String, style: &str, text: &str, rows: usize) -> std::fmt::Result {
+    write!(buf, r#"<table:table-cell table:style-name="{style}""#)?;
+    if rows > 1 {
+        write!(buf, r#" table:number-rows-spanned="{rows}""#)?;
+    }
+    if text.is_empty() {
+        return write!(buf, "/>");
+    }
+    write!(buf, r#" office:value-type="string">"#)?;
+    for line in text.lines() {
+        write!(buf, "<text:p>{}</text:p>", escape(line))?;
+    }
+    write!(buf, "</table:table-cell>")
+}
+
+#[cfg(test)]
+mod tests {
+    use calamine::{Data, Ods, Reader};
+
+    use super::*;
+
+    #[test]
+    fn layout() {
+        let s = "
+title: Spec title
+depth: 2
+
+cases:
+  - title: ログイン
+    children:
+      - id: TC-1
+        title: Valid password
+        operations:
+          - Open the <login> page
+          - Enter the password
+      - title: Invalid password
+  - title: Payment
+";
+        let spec: TestSpec = s.parse().unwrap();
+        let bytes = generate_ods(&spec, &GenerateOption::default()).unwrap();
+        assert_eq!(MIMETYPE.as_bytes(), &bytes[38..38 + MIMETYPE.len()]);
+
+        let content = content(&spec, &GenerateOption::default()).unwrap();
+        assert!(content.contains(
+            r#"<table:table-cell table:style-name="center" table:number-rows-spanned="2" office:value-type="string"><text:p>ログイン</text:p></table:table-cell>"#
+        ));
+
+        let mut ods = Ods::new(Cursor::new(bytes)).unwrap();
+        let range = ods.worksheet_range("Spec title").unwrap();
+        let cell = |row, col| range.get_value((row, col)).cloned().unwrap_or(Data::Empty);
+        assert_eq!(Data::String("No.".to_string()), cell(0, 0));
+        assert_eq!(Data::String("TC-1".to_string()), cell(1, 0));
+        assert_eq!(Data::String("ログイン".to_string()), cell(1, 1));
+        assert_eq!(
+            Data::String("1. Open the <login> page\n2. Enter the password".to_string()),
+            cell(1, 5)
+        );
+        assert_eq!(Data::Float(2.0), cell(2, 0));
+        assert_eq!(Data::Empty, cell(2, 1));
+        assert_eq!(Data::String("Payment".to_string()), cell(3, 1));
+    }
+}
@@ -0,0 +1,685 @@
+use std::collections::BTreeMap;
+use std::io::Write as _;
+use std::ptr;
+
+use anyhow::{bail, Context};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use pdf_writer::types::{CidFontType, FontFlags, SystemInfo, TextRenderingMode, UnicodeCmap};
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+use ttf_parser::{Face, GlyphId};
+
+use crate::testspec::TestSpec;
+
+use super::table::{case_text, rows, Row};
+use super::{Column, GenerateOption};
+
+/// A4 landscape, in points.
+const PAGE_WIDTH: f32 = 841.89;
+const PAGE_HEIGHT: f32 = 595.28;
+const MARGIN: f32 = 28.35;
+const FOOTER_HEIGHT: f32 = 20.0;
+const FONT_SIZE: f32 = 8.0;
+const LINE_HEIGHT: f32 = FONT_SIZE * 1.3;
+const PADDING: f32 = 3.0;
+const BORDER_WIDTH: f32 = 0.75;
+
+/// Generates a PDF of the case table in the Excel layout, on as many A4 landscape pages as
+/// needed. The header row is repeated on every page, merged item cells are continued on the
+/// next page with their title repeated, and each page has the spec title and the page number
+/// in its footer.
+///
+/// The font is embedded (subset to the used glyphs): `option.font_file` if given, else the
+/// installed font named `option.font_family`.
+pub fn generate_pdf(spec: &TestSpec, option: &GenerateOption) -> anyhow::Result<Vec<u8>> {
+    let (data, index) = load_font(option)?;
+    let face = Face::parse(&data, index).context("failed to parse the font")?;
+    let mut font = Font {
+        face,
+        used: BTreeMap::new(),
+    };
+
+    let columns = option.column_options.columns(spec)?;
+    let total: f64 = columns.iter().map(|(_, o)| o.width).sum();
+    let scale = (PAGE_WIDTH - 2.0 * MARGIN) / total as f32;
+    let widths: Vec<f32> = columns
+        .iter()
+        .map(|(_, o)| o.width as f32 * scale)
+        .collect();
+    let kinds: Vec<Column> = columns.iter().map(|(c, _)| *c).collect();
+
+    let rows = rows(spec);
+    let measure = |s: &str| font.width(s);
+    let wrap_width = |col: usize| widths[col] - 2.0 * PADDING;
+
+    let headers: Vec<Vec<String>> = columns
+        .iter()
+        .enumerate()
+        .map(|(col, (_, o))| wrap(o.header, wrap_width(col), &measure))
+        .collect();
+    let header_height = cell_height(&headers);
+
+    let cells: Vec<Vec<Vec<String>>> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            kinds
+                .iter()
+                .enumerate()
+                .map(|(col, column)| {
+                    let text = match (column, row.case()) {
+                        (Column::No, _) => match &row.item().id {
+                            Some(id) => id.clone(),
+                            None => (i + 1).to_string(),
+                        },
+                        (Column::Item(_), _) | (_, None) => String::new(),
+                        (column, Some(case)) => case_text(*column, case).unwrap_or_default(),
+                    };
+                    wrap(&text, wrap_width(col), &measure)
+                })
+                .collect()
+        })
+        .collect();
+    let mut heights: Vec<f32> = cells.iter().map(|c| cell_height(c)).collect();
+
+    // Merged item cells need room for their title across the rows they span.
+    let items: Vec<(usize, usize)> = kinds
+        .iter()
+        .enumerate()
+        .filter_map(|(col, column)| match column {
+            Column::Item(level) => Some((col, *level)),
+            _ => None,
+        })
+        .collect();
+    for &(col, level) in &items {
+        for span in super::table::spans(&rows, level) {
+            let title = match span.item {
+                Some(item) => &item.title,
+                None => continue,
+            };
+            let needed = cell_height(&[wrap(title, wrap_width(col), &measure)]);
+            let available: f32 = heights[span.first..=span.last].iter().sum();
+            if available < needed {
+                heights[span.last] += needed - available;
+            }
+        }
+    }
+
+    let body_height = PAGE_HEIGHT - 2.0 * MARGIN - FOOTER_HEIGHT - header_height;
+    let pages = paginate(&cells, &heights, body_height);
+
+    let mut contents = Vec::new();
+    for (n, pieces) in pages.iter().enumerate() {
+        let mut page = Page {
+            content: Content::new(),
+            font: &mut font,
+            option,
+            widths: &widths,
+        };
+        let mut top = PAGE_HEIGHT - MARGIN;
+        page.row(top, header_height, &headers, |_| Align::Center, true);
+        top -= header_height;
+
+        let body_top = top;
+        for piece in pieces {
+            page.row(
+                top,
+                piece.height,
+                &piece.lines,
+                |col| match kinds[col] {
+                    Column::Item(_) => Align::Skip,
+                    column if column.is_text() => Align::TopLeft,
+                    _ => Align::Center,
+                },
+                false,
+            );
+            top -= piece.height;
+        }
+        for &(col, level) in &items {
+            page.item_column(body_top, col, level, &rows, pieces);
+        }
+
+        page.footer(&spec.title, n + 1, pages.len());
+        contents.push(page.content.finish());
+    }
+
+    write_pdf(spec, &font, &data, index, contents)
+}
+
+fn load_font(option: &GenerateOption) -> anyhow::Result<(Vec<u8>, u32)> {
+    if let Some(path) = option.font_file {
+        let data = std::fs::read(path).with_context(|| format!("failed to read {path}"))?;
+        return Ok((data, 0));
+    }
+
+    let mut db = fontdb::Database::new();
+    db.load_system_fonts();
+    let query = fontdb::Query {
+        families: &[fontdb::Family::Name(option.font_family)],
+        ..Default::default()
+    };
+    let font = db
+        .query(&query)
+        .and_then(|id| db.with_face_data(id, |data, index| (data.to_vec(), index)));
+    match font {
+        Some(font) => Ok(font),
+        None => bail!(
+            "font `{}` is not installed; pass a font file with --font-file",
+            option.font_family
+        ),
+    }
+}
+
+struct Font<'a> {
+    face: Face<'a>,
+    /// The glyphs shown so far, with the character each one stands for.
+    used: BTreeMap<u16, char>,
+}
+
+impl Font<'_> {
+    fn glyph(&self, c: char) -> u16 {
+        self.face.glyph_index(c).map_or(0, |g| g.0)
+    }
+
+    /// Advance of `glyph` in thousandths of the font size.
+    fn advance(&self, glyph: u16) -> f32 {
+        let advance = self.face.glyph_hor_advance(GlyphId(glyph)).unwrap_or(0);
+        self.to_pdf_units(advance as f32)
+    }
+
+    fn to_pdf_units(&self, units: f32) -> f32 {
+        units * 1000.0 / self.face.units_per_em() as f32
+    }
+
+    /// Width of `s` in points at `FONT_SIZE`.
+    fn width(&self, s: &str) -> f32 {
+        let advance: f32 = s.chars().map(|c| self.advance(self.glyph(c))).sum();
+        advance * FONT_SIZE / 1000.0
+    }
+
+    /// Encodes `s` as the two-byte glyph IDs of the `Identity-H` encoding.
+    fn encode(&mut self, s: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for c in s.chars() {
+            let glyph = self.glyph(c);
+            self.used.entry(glyph).or_insert(c);
+            bytes.extend_from_slice(&glyph.to_be_bytes());
+        }
+        bytes
+    }
+}
+
+/// Splits `text` into lines no wider than `width`. Lines break at spaces, or between any two
+/// characters of CJK text, and words wider than a line are broken anywhere.
+fn wrap(text: &str, width: f32, measure: &impl Fn(&str) -> f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for token in tokens(paragraph) {
+            let candidate = format!("{line}{token}");
+            if line.is_empty() || measure(candidate.trim_end()) <= width {
+                line = candidate;
+            } else {
+                lines.push(line.trim_end().to_string());
+                line = token.trim_start().to_string();
+            }
+            while measure(&line) > width && line.chars().count() > 1 {
+                let mut head = String::new();
+                for c in line.chars() {
+                    if !head.is_empty() && measure(&format!("{head}{c}")) > width {
+                        break;
+                    }
+                    head.push(c);
+                }
+                line = line[head.len()..].to_string();
+                lines.push(head);
+            }
+        }
+        lines.push(line.trim_end().to_string());
+    }
+    lines
+}
+
+/// Words with their trailing spaces, and single CJK characters.
+fn tokens(s: &str) -> Vec<&str> {
+    let is_cjk = |c: char| c >= '\u{2e80}';
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut prev_space = false;
+    for (i, c) in s.char_indices() {
+        if i > start && (is_cjk(c) || (prev_space && c != ' ')) {
+            tokens.push(&s[start..i]);
+            start = i;
+        }
+        if is_cjk(c) {
+            let end = i + c.len_utf8();
+            tokens.push(&s[start..end]);
+            start = end;
+        }
+        prev_space = c == ' ';
+    }
+    if start < s.len() {
+        tokens.push(&s[start..]);
+    }
+    tokens
+}
+
+fn cell_height(cells: &[Vec<String>]) -> f32 {
+    let lines = cells.iter().map(|c| c.len()).max().unwrap_or(0).max(1);
+    lines as f32 * LINE_HEIGHT + 2.0 * PADDING
+}
+
+/// A row, or the part of a row, placed on a page.
+#[derive(Debug)]
+struct Piece {
+    row: usize,
+    height: f32,
+    lines: Vec<Vec<String>>,
+}
+
+/// Places the rows on pages with `body_height` points below the header. A row that doesn't
+/// fit goes to the next page, and a row taller than a page is split between its lines.
+fn paginate(cells: &[Vec<Vec<String>>], heights: &[f32], body_height: f32) -> Vec<Vec<Piece>> {
+    let mut pages = Vec::new();
+    let mut page = Vec::new();
+    let mut used = 0.0;
+    for (row, (lines, &height)) in cells.iter().zip(heights).enumerate() {
+        let mut lines = lines.clone();
+        let mut height = height;
+        loop {
+            let remaining = body_height - used;
+            if height <= remaining + 0.01 {
+                page.push(Piece { row, height, lines });
+                used += height;
+                break;
+            }
+            let fitting = ((remaining - 2.0 * PADDING) / LINE_HEIGHT).floor();
+            if (height <= body_height && used > 0.0) || fitting < 1.0 {
+                pages.push(std::mem::take(&mut page));
+                used = 0.0;
+                continue;
+            }
+
+            let fitting = fitting as usize;
+            let rest: Vec<Vec<String>> = lines
+                .iter_mut()
+                .map(|l| l.split_off(fitting.min(l.len())))
+                .collect();
+            page.push(Piece {
+                row,
+                height: remaining,
+                lines,
+            });
+            pages.push(std::mem::take(&mut page));
+            used = 0.0;
+            height = cell_height(&rest);
+            lines = rest;
+        }
+    }
+    pages.push(page);
+    pages
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Align {
+    Center,
+    TopLeft,
+    /// The cell is drawn separately, e.g. a merged item cell.
+    Skip,
+}
+
+struct Page<'a, 'b, 'c> {
+    content: Content,
+    font: &'a mut Font<'b>,
+    option: &'a GenerateOption<'c>,
+    widths: &'a [f32],
+}
+
+impl Page<'_, '_, '_> {
+    fn row(
+        &mut self,
+        top: f32,
+        height: f32,
+        cells: &[Vec<String>],
+        align: impl Fn(usize) -> Align,
+        header: bool,
+    ) {
+        let mut left = MARGIN;
+        for (col, lines) in cells.iter().enumerate() {
+            let width = self.widths[col];
+            if align(col) != Align::Skip {
+                self.cell(left, top, width, height, lines, align(col), header);
+            }
+            left += width;
+        }
+    }
+
+    /// Draws the cells of an item column, merging consecutive pieces of the same item.
+    fn item_column(&mut self, top: f32, col: usize, level: usize, rows: &[Row], pieces: &[Piece]) {
+        let left = MARGIN + self.widths[..col].iter().sum::<f32>();
+        let width = self.widths[col];
+        let mut top = top;
+        let mut i = 0;
+        while i < pieces.len() {
+            let item = rows[pieces[i].row].items[level];
+            let mut height = pieces[i].height;
+            let mut j = i + 1;
+            while j < pieces.len() {
+                let next = rows[pieces[j].row].items[level];
+                match (item, next) {
+                    (Some(a), Some(b)) if ptr::eq(a, b) => height += pieces[j].height,
+                    _ => break,
+                }
+                j += 1;
+            }
+            let lines = match item {
+                Some(item) => wrap(&item.title, width - 2.0 * PADDING, &|s| self.font.width(s)),
+                None => Vec::new(),
+            };
+            self.cell(left, top, width, height, &lines, Align::Center, false);
+            top -= height;
+            i = j;
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn cell(
+        &mut self,
+        left: f32,
+        top: f32,
+        width: f32,
+        height: f32,
+        lines: &[String],
+        align: Align,
+        header: bool,
+    ) {
+        let (font_color, bg_color) = if header {
+            (self.option.header_font_color, self.option.header_bg_color)
+        } else {
+            (self.option.body_font_color, self.option.body_bg_color)
+        };
+
+        let [r, g, b] = rgb(bg_color);
+        self.content.set_fill_rgb(r, g, b);
+        let [r, g, b] = rgb(self.option.border_color);
+        self.content.set_stroke_rgb(r, g, b);
+        self.content.set_line_width(BORDER_WIDTH);
+        self.content
+            .rect(left, top - height, width, height)
+            .fill_nonzero_and_stroke();
+
+        let fitting = ((height - 2.0 * PADDING) / LINE_HEIGHT).floor().max(0.0) as usize;
+        let lines = &lines[..lines.len().min(fitting)];
+        if lines.is_empty() {
+            return;
+        }
+
+        let ascent = self.font.to_pdf_units(self.font.face.ascender() as f32) / 1000.0;
+        let text_height = lines.len() as f32 * LINE_HEIGHT;
+        let first_top = match align {
+            Align::TopLeft => top - PADDING,
+            _ => top - (height - text_height) / 2.0,
+        };
+
+        let [r, g, b] = rgb(font_color);
+        self.content.set_fill_rgb(r, g, b);
+        self.content.begin_text();
+        self.content
+            .set_font(Name(b"F1"), FONT_SIZE)
+            .set_text_rendering_mode(if header {
+                // The embedded font has a single weight, so headers are emboldened by
+                // stroking the outlines.
+                TextRenderingMode::FillStroke
+            } else {
+                TextRenderingMode::Fill
+            });
+        if header {
+            self.content
+                .set_stroke_rgb(r, g, b)
+                .set_line_width(FONT_SIZE / 30.0);
+        }
+        for (i, line) in lines.iter().enumerate() {
+            let x = match align {
+                Align::TopLeft => left + PADDING,
+                _ => left + (width - self.font.width(line)) / 2.0,
+            };
+            let baseline = first_top
+                - i as f32 * LINE_HEIGHT
+                - (LINE_HEIGHT - FONT_SIZE) / 2.0
+                - ascent * FONT_SIZE;
+            let bytes = self.font.encode(line);
+            self.content
+                .set_text_matrix([1.0, 0.0, 0.0, 1.0, x, baseline])
+                .show(Str(&bytes));
+        }
+        self.content.end_text();
+    }
+
+    fn footer(&mut self, title: &str, page: usize, pages: usize) {
+        let [r, g, b] = rgb(self.option.body_font_color);
+        let number = format!("{page} / {pages}");
+        let number_width = self.font.width(&number);
+        let baseline = MARGIN;
+
+        self.content.set_fill_rgb(r, g, b);
+        self.content.begin_text();
+        self.content
+            .set_font(Name(b"F1"), FONT_SIZE)
+            .set_text_rendering_mode(TextRenderingMode::Fill);
+        let title = self.font.encode(title);
+        self.content
+            .set_text_matrix([1.0, 0.0, 0.0, 1.0, MARGIN, baseline])
+            .show(Str(&title));
+        let number = self.font.encode(&number);
+        self.content
+            .set_text_matrix([
+                1.0,
+                0.0,
+                0.0,
+                1.0,
+                PAGE_WIDTH - MARGIN - number_width,
+                baseline,
+            ])
+            .show(Str(&number));
+        self.content.end_text();
+    }
+}
+
+fn rgb(color: u32) -> [f32; 3] {
+    [16, 8, 0].map(|shift| ((color >> shift) & 0xff) as f32 / 255.0)
+}
+
+fn deflate(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+/// Assembles the document: the pages with their content streams, and the font as a `Type0`
+/// font with `Identity-H` encoding, so that the content can address any glyph of it.
+fn write_pdf(
+    spec: &TestSpec,
+    font: &Font,
+    data: &[u8],
+    index: u32,
+    contents: Vec<Vec<u8>>,
+) -> anyhow::Result<Vec<u8>> {
+    let mut next = Ref::new(1);
+    let catalog_id = next.bump();
+    let pages_id = next.bump();
+    let info_id = next.bump();
+    let font_id = next.bump();
+    let cid_font_id = next.bump();
+    let descriptor_id = next.bump();
+    let font_file_id = next.bump();
+    let cmap_id = next.bump();
+    let page_ids: Vec<_> = contents
+        .iter()
+        .map(|_| (next.bump(), next.bump()))
+        .collect();
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(pages_id);
+    pdf.pages(pages_id)
+        .kids(page_ids.iter().map(|(page, _)| *page))
+        .count(page_ids.len() as i32);
+    pdf.document_info(info_id)
+        .title(TextStr(&spec.title))
+        .creator(TextStr("testspecgen"));
+
+    for ((page_id, content_id), content) in page_ids.iter().zip(contents) {
+        let mut page = pdf.page(*page_id);
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+            .parent(pages_id)
+            .contents(*content_id);
+        page.resources().fonts().pair(Name(b"F1"), font_id);
+        page.finish();
+        pdf.stream(*content_id, &deflate(&content)?)
+            .filter(Filter::FlateDecode);
+    }
+
+    let face = &font.face;
+    let postscript_name = face
+        .names()
+        .into_iter()
+        .find(|n| n.name_id == ttf_parser::name_id::POST_SCRIPT_NAME)
+        .and_then(|n| n.to_string())
+        .unwrap_or_else(|| "Font".to_string());
+    // Subset fonts are named with a tag of six capital letters.
+    let base_font = format!("TSGSUB+{postscript_name}");
+    let base_font = Name(base_font.as_bytes());
+    let system_info = SystemInfo {
+        registry: Str(b"Adobe"),
+        ordering: Str(b"Identity"),
+        supplement: 0,
+    };
+    let cff = face.tables().cff.is_some();
+
+    pdf.type0_font(font_id)
+        .base_font(base_font)
+        .encoding_predefined(Name(b"Identity-H"))
+        .descendant_font(cid_font_id)
+        .to_unicode(cmap_id);
+
+    let mut cid_font = pdf.cid_font(cid_font_id);
+    cid_font
+        .subtype(if cff {
+            CidFontType::Type0
+        } else {
+            CidFontType::Type2
+        })
+        .base_font(base_font)
+        .system_info(system_info)
+        .font_descriptor(descriptor_id)
+        .default_width(0.0);
+    if !cff {
+        cid_font.cid_to_gid_map_predefined(Name(b"Identity"));
+    }
+    let mut widths = cid_font.widths();
+    for &glyph in font.used.keys() {
+        widths.consecutive(glyph, [font.advance(glyph)]);
+    }
+    widths.finish();
+    cid_font.finish();
+
+    let bbox = face.global_bounding_box();
+    let units = |v: i16| font.to_pdf_units(v as f32);
+    let mut descriptor = pdf.font_descriptor(descriptor_id);
+    descriptor
+        .name(base_font)
+        .flags(FontFlags::SYMBOLIC)
+        .bbox(Rect::new(
+            units(bbox.x_min),
+            units(bbox.y_min),
+            units(bbox.x_max),
+            units(bbox.y_max),
+        ))
+        .italic_angle(face.italic_angle().unwrap_or(0.0))
+        .ascent(units(face.ascender()))
+        .descent(units(face.descender()))
+        .cap_height(units(face.capital_height().unwrap_or(face.ascender())))
+        .stem_v(80.0);
+    if cff {
+        descriptor.font_file3(font_file_id);
+    } else {
+        descriptor.font_file2(font_file_id);
+    }
+    descriptor.finish();
+
+    let glyphs: Vec<u16> = font.used.keys().copied().collect();
+    let subset = subsetter::subset(data, index, subsetter::Profile::pdf(&glyphs))
+        .map_err(|e| anyhow::anyhow!("failed to subset the font: {e:?}"))?;
+    let subset = deflate(&subset)?;
+    let mut font_file = pdf.stream(font_file_id, &subset);
+    font_file.filter(Filter::FlateDecode);
+    if cff {
+        font_file.pair(Name(b"Subtype"), Name(b"OpenType"));
+    }
+    font_file.finish();
+
+    let mut cmap = UnicodeCmap::new(Name(b"Custom"), system_info);
+    for (&glyph, &c) in &font.used {
+        cmap.pair(glyph, c);
+    }
+    pdf.stream(cmap_id, &cmap.finish());
+
+    Ok(pdf.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measure(s: &str) -> f32 {
+        s.chars().count() as f32
+    }
+
+    #[test]
+    fn wrapping() {
+        assert_eq!(
+            vec!["Open the", "login page", "abcdefghij", "klm"],
+            wrap("Open the login page\nabcdefghijklm", 10.0, &measure)
+        );
+        assert_eq!(
+            vec!["ログイン画面を", "開く"],
+            wrap("ログイン画面を開く", 7.0, &measure)
+        );
+        assert!(wrap("", 7.0, &measure).is_empty());
+    }
+
+    #[test]
+    fn pagination() {
+        let one_line = cell_height(&[vec![String::new()]]);
+        let cells: Vec<_> = (0..5)
+            .map(|i| vec![vec![i.to_string()]; if i == 3 { 12 } else { 1 }])
+            .map(|mut c| {
+                c.truncate(1);
+                c
+            })
+            .collect();
+        let heights = vec![one_line; 5];
+        let pages = paginate(&cells, &heights, one_line * 2.0);
+        let rows: Vec<Vec<usize>> = pages
+            .iter()
+            .map(|p| p.iter().map(|piece| piece.row).collect())
+            .collect();
+        assert_eq!(vec![vec![0, 1], vec![2, 3], vec![4]], rows);
+
+        // A row taller than a page is split between its lines.
+        let tall = vec![vec![(0..10).map(|i| i.to_string()).collect::<Vec<_>>()]];
+        let body = LINE_HEIGHT * 4.0 + 2.0 * PADDING;
+        let pages = paginate(&tall, &[cell_height(&tall[0])], body);
+        let lines: Vec<usize> = pages.iter().map(|p| p[0].lines[0].len()).collect();
+        assert_eq!(vec![4, 4, 2], lines);
+    }
+
+    #[test]
+    fn missing_font() {
+        let spec: TestSpec = "title: Spec title\ncases: []".parse().unwrap();
+        let option = GenerateOption {
+            font_file: Some("no/such/font.ttf"),
+            ..Default::default()
+        };
+        assert!(generate_pdf(&spec, &option).is_err());
+    }
+}
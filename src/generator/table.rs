@@ -0,0 +1,250 @@
+use std::ptr;
+
+use crate::testspec::{ExecutionResult, Item, TestSpec};
+
+use super::Column;
+
+/// One row of a case table: the item at each level on the path to a test case.
+/// Levels below a category without children are `None`.
+#[derive(Debug)]
+pub struct Row<'a> {
+    pub items: Vec<Option<&'a Item>>,
+}
+
+impl<'a> Row<'a> {
+    /// The deepest item on the row.
+    pub fn item(&self) -> &'a Item {
+        match self.items.iter().rev().flatten().next() {
+            Some(item) => item,
+            None => unreachable!(),
+        }
+    }
+
+    pub fn case(&self) -> Option<&'a Item> {
+        self.items.last().copied().flatten()
+    }
+}
+
+/// A run of consecutive rows sharing the same item at one level, i.e. a merged cell.
+#[derive(Debug)]
+pub struct Span<'a> {
+    pub first: usize,
+    pub last: usize,
+    pub item: Option<&'a Item>,
+}
+
+pub fn rows(spec: &TestSpec) -> Vec<Row<'_>> {
+    let mut rows = Vec::new();
+    push_rows(&spec.cases, spec.depth, &mut Vec::new(), &mut rows);
+    rows
+}
+
+fn push_rows<'a>(
+    items: &'a [Item],
+    depth: usize,
+    path: &mut Vec<&'a Item>,
+    rows: &mut Vec<Row<'a>>,
+) {
+    for item in items {
+        path.push(item);
+        if item.children.is_empty() {
+            let mut items: Vec<_> = path.iter().copied().map(Some).collect();
+            items.resize(depth, None);
+            rows.push(Row { items });
+        } else {
+            push_rows(&item.children, depth, path, rows);
+        }
+        path.pop();
+    }
+}
+
+/// Text of `case` in a per-case column, or `None` for the No. and item columns.
+pub fn case_text(column: Column, case: &Item) -> Option<String> {
+    let text = match column {
+        Column::No | Column::Item(_) => return None,
+        Column::Operator => result_text(case, |r| r.operator.clone()),
+        Column::Result => result_text(case, |r| Some(r.status.to_string())),
+        Column::Date => result_text(case, |r| r.date.clone()),
+        Column::Note => result_text(case, |r| r.note.clone()),
+        Column::Defect => result_text(case, |r| r.defect.clone()),
+        Column::Environment => bullets_text(&case.environment),
+        Column::Preconditions => bullets_text(&case.preconditions),
+        Column::TestData => bullets_text(&case.test_data),
+        Column::Parameters => parameters_text(case),
+        Column::Operations => operations_text(case),
+        Column::Confirmations => confirmations_text(case),
+        Column::Remarks => bullets_text(&case.remarks),
+    };
+    Some(text)
+}
+
+fn result_text(case: &Item, field: impl Fn(&ExecutionResult) -> Option<String>) -> String {
+    case.result.as_ref().and_then(field).unwrap_or_default()
+}
+
+/// Text of the parameters cell: `name: value, value` for each parameter, then
+/// `name=value, name=value` for each example row.
+pub fn parameters_text(case: &Item) -> String {
+    let parameters = case
+        .parameters
+        .iter()
+        .map(|(name, values)| format!("{name}: {}", values.join(", ")));
+    let examples = case.examples.iter().map(|row| {
+        row.iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    });
+    parameters.chain(examples).collect::<Vec<_>>().join("\n")
+}
+
+/// Text of the operations cell: the plain operations followed by the steps, numbered
+/// continuously.
+pub fn operations_text(case: &Item) -> String {
+    let operations = case.operations.iter().map(|o| o.to_string());
+    let steps = case.steps.iter().map(|s| match &s.data {
+        Some(data) => format!("{} ({data})", s.action),
+        None => s.action.to_string(),
+    });
+    operations
+        .chain(steps)
+        .enumerate()
+        .map(|(i, o)| format!("{}. {o}", i + 1))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Text of the confirmations cell. Expected results of steps carry the step number so they
+/// line up with `operations_text`.
+pub fn confirmations_text(case: &Item) -> String {
+    let confirmations = case.confirmations.iter().map(|c| format!("- {c}"));
+    let expected = case.steps.iter().enumerate().filter_map(|(i, s)| {
+        let order = case.operations.len() + i + 1;
+        s.expected.as_ref().map(|e| format!("{order}. {e}"))
+    });
+    confirmations.chain(expected).collect::<Vec<_>>().join("\n")
+}
+
+pub fn bullets_text(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|v| format!("- {v}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Groups `rows` by the item at `level` (0-based). Empty cells are never merged.
+pub fn spans<'a>(rows: &[Row<'a>], level: usize) -> Vec<Span<'a>> {
+    let mut spans: Vec<Span> = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        let item = row.items[level];
+        match (spans.last_mut(), item) {
+            (Some(span), Some(item)) if matches!(span.item, Some(s) if ptr::eq(s, item)) => {
+                span.last = i;
+            }
+            _ => spans.push(Span {
+                first: i,
+                last: i,
+                item,
+            }),
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merged_spans() {
+        let s = "
+title: Spec title
+
+cases:
+  - title: Primary 1
+    children:
+      - title: Secondary 1-1
+        children:
+          - title: Tertiary 1-1-1
+          - title: Tertiary 1-1-2
+      - title: Secondary 1-2
+  - title: Primary 2
+";
+        let spec: TestSpec = s.parse().unwrap();
+        let rows = rows(&spec);
+        assert_eq!(4, rows.len());
+        assert_eq!("Secondary 1-2", rows[2].item().title);
+        assert_eq!(None, rows[2].case());
+
+        let titles = |level| {
+            spans(&rows, level)
+                .iter()
+                .map(|s| (s.first, s.last, s.item.map(|i| &i.title[..])))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![(0, 2, Some("Primary 1")), (3, 3, Some("Primary 2"))],
+            titles(0)
+        );
+        assert_eq!(
+            vec![
+                (0, 1, Some("Secondary 1-1")),
+                (2, 2, Some("Secondary 1-2")),
+                (3, 3, None)
+            ],
+            titles(1)
+        );
+    }
+
+    #[test]
+    fn paired_steps() {
+        let s = "
+title: Spec title
+depth: 1
+
+cases:
+  - title: Case 1
+    operations:
+      - Open the login page
+    confirmations:
+      - The login page is shown
+    steps:
+      - action: Enter the password
+        data: hunter2
+      - action: Press the login button
+        expected: The top page is shown
+";
+        let spec: TestSpec = s.parse().unwrap();
+        let case = &spec.cases[0];
+        assert_eq!(
+            "1. Open the login page\n2. Enter the password (hunter2)\n3. Press the login button",
+            operations_text(case)
+        );
+        assert_eq!(
+            "- The login page is shown\n3. The top page is shown",
+            confirmations_text(case)
+        );
+    }
+
+    #[test]
+    fn result_cells() {
+        let s = "
+title: Spec title
+depth: 1
+
+cases:
+  - title: Case 1
+    result:
+      status: blocked
+      operator: Alice
+  - title: Case 2
+";
+        let spec: TestSpec = s.parse().unwrap();
+        let text = |column, i: usize| case_text(column, &spec.cases[i]).unwrap();
+        assert_eq!("Alice", text(Column::Operator, 0));
+        assert_eq!("Blocked", text(Column::Result, 0));
+        assert_eq!("", text(Column::Date, 0));
+        assert_eq!("", text(Column::Result, 1));
+    }
+}
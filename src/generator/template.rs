@@ -0,0 +1,117 @@
+use std::collections::{BTreeSet, HashMap};
+
+use serde::Serialize;
+use tera::{Context, Tera, Value};
+
+use crate::testspec::{Status, TestSpec};
+
+use super::GenerateOption;
+
+/// Renders `template`, a [Tera](https://keats.github.io/tera/) template, with the spec as
+/// `spec`, the counts of test cases by result as `summary`, the section labels of the locale as
+/// `labels` and the version of this tool as `version`. As with Tera itself, values are
+/// HTML-escaped if `name` ends with `.html`, `.htm` or `.xml`.
+///
+/// Besides the Tera built-ins, templates can use the `table_cell` filter, which escapes `|`
+/// and turns line breaks into `<br>` for Markdown tables, and the `columns` filter, which
+/// returns the sorted keys of a list of objects such as `examples`.
+pub fn generate_template(
+    spec: &TestSpec,
+    option: &GenerateOption,
+    name: &str,
+    template: &str,
+) -> anyhow::Result<String> {
+    let mut tera = Tera::default();
+    tera.register_filter("table_cell", table_cell);
+    tera.register_filter("columns", columns);
+    tera.add_raw_template(name, template)?;
+
+    let mut context = Context::new();
+    context.insert("spec", spec);
+    context.insert("summary", &Summary::new(spec));
+    context.insert("labels", &option.locale.sections);
+    context.insert("version", env!("CARGO_PKG_VERSION"));
+    Ok(tera.render(name, &context)?)
+}
+
+/// Counts of the test cases by result.
+#[derive(Debug, Default, Serialize)]
+struct Summary {
+    total: usize,
+    pass: usize,
+    fail: usize,
+    blocked: usize,
+    skip: usize,
+    untested: usize,
+}
+
+impl Summary {
+    fn new(spec: &TestSpec) -> Summary {
+        let mut summary = Summary::default();
+        for case in spec.test_cases() {
+            summary.total += 1;
+            let count = match case.result.as_ref().map(|r| r.status) {
+                Some(Status::Pass) => &mut summary.pass,
+                Some(Status::Fail) => &mut summary.fail,
+                Some(Status::Blocked) => &mut summary.blocked,
+                Some(Status::Skip) => &mut summary.skip,
+                None => &mut summary.untested,
+            };
+            *count += 1;
+        }
+        summary
+    }
+}
+
+fn table_cell(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let s = tera::try_get_value!("table_cell", "value", String, value);
+    Ok(Value::String(s.replace('|', "\\|").replace('\n', "<br>")))
+}
+
+fn columns(value: &Value, _: &HashMap<String, Value>) -> tera::Result<Value> {
+    let rows = tera::try_get_value!("columns", "value", Vec<tera::Map<String, Value>>, value);
+    let names: BTreeSet<&String> = rows.iter().flat_map(|row| row.keys()).collect();
+    Ok(Value::from_iter(names.into_iter().cloned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_template() {
+        let spec: TestSpec = "
+title: Spec <title>
+depth: 1
+cases:
+  - title: Case 1
+    result:
+      status: fail
+  - title: Case 2
+"
+        .parse()
+        .unwrap();
+        let template = "h1. {{ spec.title }}\n{% for case in spec.cases %}* {{ case.title }}\n{% endfor %}{{ summary.fail }}/{{ summary.total }} failed";
+        assert_eq!(
+            "h1. Spec <title>\n* Case 1\n* Case 2\n1/2 failed",
+            generate_template(&spec, &GenerateOption::default(), "spec.wiki", template).unwrap()
+        );
+        assert_eq!(
+            "Spec &lt;title&gt;",
+            generate_template(
+                &spec,
+                &GenerateOption::default(),
+                "spec.html",
+                "{{ spec.title }}"
+            )
+            .unwrap()
+        );
+        assert!(generate_template(
+            &spec,
+            &GenerateOption::default(),
+            "spec.wiki",
+            "{{ spec.missing }}"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,16 @@
+mod excel;
+mod gherkin;
+mod markdown;
+
+pub use excel::import_excel;
+pub use gherkin::import_gherkin;
+pub use markdown::import_markdown;
+
+use crate::testspec::TestSpec;
+
+/// A spec read from another format, with the problems that did not stop the import.
+#[derive(Debug)]
+pub struct Imported {
+    pub spec: TestSpec,
+    pub warnings: Vec<String>,
+}
@@ -0,0 +1,456 @@
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use anyhow::{bail, ensure, Context};
+use calamine::{open_workbook_from_rs, Data, DataType, Dimensions, Range, Reader, Xlsx};
+
+use super::Imported;
+use crate::generator::{Column, ColumnsOption};
+use crate::testspec::{ExecutionResult, Item, Step, TestSpec};
+
+/// Reads a workbook laid out like the output of `generate_excel` back into a spec. The headers
+/// must match `option`, and the title is taken from the sheet name; columns with any other
+/// header are skipped with a warning. Recorded results are attached to the test cases.
+///
+/// Steps cannot be told apart from operations by their text alone, so every operation from
+/// the first one with a numbered expected result in the confirmations cell becomes a step,
+/// and a step written as `action (data)` gets its data back.
+pub fn import_excel(bytes: &[u8], option: &ColumnsOption) -> anyhow::Result<Imported> {
+    let mut book: Xlsx<_> = open_workbook_from_rs(Cursor::new(bytes))?;
+    book.load_merged_regions()?;
+
+    let title = match book.sheet_names().first() {
+        Some(name) => name.clone(),
+        None => bail!("the workbook has no sheet"),
+    };
+    let range = book.worksheet_range(&title)?;
+    let merged: Vec<Dimensions> = book
+        .merged_regions_by_sheet(&title)
+        .into_iter()
+        .map(|(_, _, d)| *d)
+        .collect();
+    let sheet = Sheet { range, merged };
+
+    let mut warnings = Vec::new();
+    let columns = header_columns(&sheet, option, &mut warnings)?;
+    let depth = columns
+        .iter()
+        .filter(|(c, _)| matches!(c, Column::Item(_)))
+        .count();
+    ensure!(depth > 0, "no item column found in the header row");
+
+    let (first_row, last_row) = match (sheet.range.start(), sheet.range.end()) {
+        (Some(start), Some(end)) => (start.0 + 1, end.0),
+        _ => bail!("the sheet is empty"),
+    };
+
+    let mut cases: Vec<Item> = Vec::new();
+    let mut last_keys: Vec<u32> = Vec::new();
+    for row in first_row..=last_row {
+        let path: Vec<(u32, String)> = columns
+            .iter()
+            .filter(|(c, _)| matches!(c, Column::Item(_)))
+            .map_while(|(_, col)| sheet.item_cell(row, *col))
+            .collect();
+        if path.is_empty() {
+            continue;
+        }
+
+        let mut items = &mut cases;
+        let mut same = true;
+        for (level, (key, title)) in path.iter().enumerate() {
+            same = same && last_keys.get(level) == Some(key);
+            if !same {
+                items.push(Item {
+                    title: title.clone(),
+                    ..Default::default()
+                });
+            }
+            let item = match items.last_mut() {
+                Some(item) => item,
+                None => unreachable!(),
+            };
+            if level + 1 == depth {
+                read_case(&sheet, row, &columns, item)
+                    .with_context(|| format!("invalid row {}", row + 1))?;
+            }
+            items = &mut item.children;
+        }
+        last_keys = path.into_iter().map(|(key, _)| key).collect();
+    }
+
+    let spec = TestSpec {
+        title,
+        depth,
+        config: None,
+        cases,
+    };
+    spec.validate()?;
+    Ok(Imported { spec, warnings })
+}
+
+struct Sheet {
+    range: Range<Data>,
+    merged: Vec<Dimensions>,
+}
+
+impl Sheet {
+    fn text(&self, row: u32, col: u32) -> String {
+        match self.range.get_value((row, col)) {
+            Some(Data::String(s)) => s.trim().to_string(),
+            Some(Data::Float(f)) if f.fract() == 0.0 => format!("{f:.0}"),
+            Some(d @ (Data::DateTime(_) | Data::DateTimeIso(_))) => match d.as_date() {
+                Some(date) => date.to_string(),
+                None => d.to_string(),
+            },
+            Some(Data::Empty) | None => String::new(),
+            Some(d) => d.to_string(),
+        }
+    }
+
+    /// Returns the row that identifies the item cell at `row` (the first row of its merged
+    /// range) together with the item title, or `None` for an empty cell.
+    fn item_cell(&self, row: u32, col: u32) -> Option<(u32, String)> {
+        let key = self
+            .merged
+            .iter()
+            .find(|d| d.contains(row, col))
+            .map_or(row, |d| d.start.0);
+        let title = self.text(key, col);
+        if title.is_empty() {
+            None
+        } else {
+            Some((key, title))
+        }
+    }
+}
+
+fn header_columns(
+    sheet: &Sheet,
+    option: &ColumnsOption,
+    warnings: &mut Vec<String>,
+) -> anyhow::Result<Vec<(Column, u32)>> {
+    let (start, end) = match (sheet.range.start(), sheet.range.end()) {
+        (Some(start), Some(end)) => (start, end),
+        _ => bail!("the sheet is empty"),
+    };
+
+    let mut headers: Vec<(Column, &str)> = vec![
+        (Column::No, option.no_column.header),
+        (Column::Operator, option.operator_column.header),
+        (Column::Result, option.result_column.header),
+        (Column::Date, option.date_column.header),
+        (Column::Note, option.note_column.header),
+        (Column::Defect, option.defect_column.header),
+        (Column::Environment, option.environment_column.header),
+        (Column::Preconditions, option.preconditions_column.header),
+        (Column::TestData, option.test_data_column.header),
+        (Column::Parameters, option.parameters_column.header),
+        (Column::Operations, option.operations_column.header),
+        (Column::Confirmations, option.confirmations_column.header),
+        (Column::Remarks, option.remarks_column.header),
+    ];
+    headers.extend(
+        option
+            .item_columns
+            .iter()
+            .enumerate()
+            .map(|(level, o)| (Column::Item(level), o.header)),
+    );
+
+    let mut columns = Vec::new();
+    for col in start.1..=end.1 {
+        let text = sheet.text(start.0, col);
+        if text.is_empty() {
+            continue;
+        }
+        match headers.iter().find(|(_, h)| *h == text) {
+            Some((column, _)) => columns.push((*column, col)),
+            None => warnings.push(format!(
+                "skipped column {} with the unknown header `{text}`",
+                column_name(col)
+            )),
+        }
+    }
+
+    let levels: Vec<usize> = columns
+        .iter()
+        .filter_map(|(c, _)| match c {
+            Column::Item(level) => Some(*level),
+            _ => None,
+        })
+        .collect();
+    ensure!(
+        levels.iter().enumerate().all(|(i, level)| i == *level),
+        "item columns must start at the first level and be in order"
+    );
+
+    Ok(columns)
+}
+
+fn read_case(
+    sheet: &Sheet,
+    row: u32,
+    columns: &[(Column, u32)],
+    case: &mut Item,
+) -> anyhow::Result<()> {
+    let cell = |column| {
+        columns
+            .iter()
+            .find(|(c, _)| *c == column)
+            .map(|(_, col)| sheet.text(row, *col))
+            .filter(|s| !s.is_empty())
+    };
+
+    // IDs are written as text and row numbers as numbers.
+    case.id = columns
+        .iter()
+        .find(|(c, _)| *c == Column::No)
+        .and_then(|(_, col)| match sheet.range.get_value((row, *col)) {
+            Some(Data::String(id)) if !id.trim().is_empty() => Some(id.trim().to_string()),
+            _ => None,
+        });
+    case.environment = bullets(&cell(Column::Environment).unwrap_or_default());
+    case.preconditions = bullets(&cell(Column::Preconditions).unwrap_or_default());
+    case.test_data = bullets(&cell(Column::TestData).unwrap_or_default());
+    case.remarks = bullets(&cell(Column::Remarks).unwrap_or_default());
+
+    for line in lines(&cell(Column::Parameters).unwrap_or_default()) {
+        match line.split_once(": ") {
+            Some((name, values)) if !name.contains('=') => {
+                let values = values.split(", ").map(|v| v.trim().to_string()).collect();
+                case.parameters.insert(name.to_string(), values);
+            }
+            _ => case.examples.push(
+                line.split(", ")
+                    .filter_map(|pair| pair.split_once('='))
+                    .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                    .collect(),
+            ),
+        }
+    }
+
+    let operations = numbered(&cell(Column::Operations).unwrap_or_default());
+    let mut expected = BTreeMap::new();
+    for line in lines(&cell(Column::Confirmations).unwrap_or_default()) {
+        match split_number(line) {
+            Some((order, text)) => {
+                expected.insert(order, text.to_string());
+            }
+            None => case.confirmations.push(strip_bullet(line).to_string()),
+        }
+    }
+    let first_step = expected.keys().next().map_or(operations.len(), |o| o - 1);
+    for (i, operation) in operations.into_iter().enumerate() {
+        if i < first_step {
+            case.operations.push(operation);
+        } else {
+            let (action, data) = split_data(&operation);
+            case.steps.push(Step {
+                action,
+                expected: expected.remove(&(i + 1)),
+                data,
+            });
+        }
+    }
+    if let Some(order) = expected.keys().next() {
+        bail!("no operation {order} for the expected result");
+    }
+
+    case.result = match cell(Column::Result) {
+        Some(status) => Some(ExecutionResult {
+            status: status.parse()?,
+            operator: cell(Column::Operator),
+            date: cell(Column::Date),
+            note: cell(Column::Note),
+            defect: cell(Column::Defect),
+        }),
+        None => None,
+    };
+
+    Ok(())
+}
+
+fn lines(text: &str) -> impl Iterator<Item = &str> {
+    text.lines().map(str::trim).filter(|l| !l.is_empty())
+}
+
+fn bullets(text: &str) -> Vec<String> {
+    lines(text).map(|l| strip_bullet(l).to_string()).collect()
+}
+
+fn numbered(text: &str) -> Vec<String> {
+    lines(text)
+        .map(|l| split_number(l).map_or(l, |(_, t)| t).to_string())
+        .collect()
+}
+
+fn strip_bullet(line: &str) -> &str {
+    line.strip_prefix("- ").unwrap_or(line).trim()
+}
+
+/// Splits `action (data)` into the action and the data, the inverse of how steps with data
+/// are written.
+fn split_data(operation: &str) -> (String, Option<String>) {
+    if let Some(inner) = operation.strip_suffix(')') {
+        let mut open = 0;
+        for (i, c) in inner.char_indices().rev() {
+            match c {
+                ')' => open += 1,
+                '(' if open > 0 => open -= 1,
+                '(' => {
+                    return match inner[..i].strip_suffix(' ') {
+                        Some(action) if !action.trim().is_empty() => {
+                            (action.to_string(), Some(inner[i + 1..].to_string()))
+                        }
+                        _ => (operation.to_string(), None),
+                    };
+                }
+                _ => {}
+            }
+        }
+    }
+    (operation.to_string(), None)
+}
+
+/// The spreadsheet name of the 0-based column `col`, e.g. `A` or `AB`.
+fn column_name(col: u32) -> String {
+    let mut name = String::new();
+    let mut n = col + 1;
+    while n > 0 {
+        name.insert(0, char::from(b'A' + ((n - 1) % 26) as u8));
+        n = (n - 1) / 26;
+    }
+    name
+}
+
+/// Splits `3. text` into `(3, "text")`.
+fn split_number(line: &str) -> Option<(usize, &str)> {
+    let (number, text) = line.split_once(". ")?;
+    let number = number.parse().ok().filter(|n| *n > 0)?;
+    Some((number, text.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::generator::{generate_excel, GenerateOption};
+    use crate::testspec::Status;
+
+    #[test]
+    fn round_trip() {
+        let s = "
+title: Spec title
+
+cases:
+  - title: Primary 1
+    children:
+      - title: Secondary 1-1
+        children:
+          - id: TC-1
+            title: Tertiary 1-1-1
+            preconditions:
+              - Signed out
+            operations:
+              - Open the login page
+            confirmations:
+              - The login page is shown
+            steps:
+              - action: Press the login button
+                expected: The top page is shown
+                data: alice (admin)
+            result:
+              status: fail
+              operator: Alice
+              defect: BUG-12
+          - title: Tertiary 1-1-2
+            parameters:
+              browser: [chrome, firefox]
+            examples:
+              - role: admin
+                locale: en
+              - role: guest
+            remarks:
+              - Flaky
+      - title: Secondary 1-2
+  - title: Primary 2
+    children:
+      - title: Secondary 2-1
+        children:
+          - id: '7'
+            title: Tertiary 2-1-1
+";
+        let spec: TestSpec = s.parse().unwrap();
+        let option = GenerateOption::default();
+        let bytes = generate_excel(&spec, &option).unwrap();
+
+        let imported = import_excel(&bytes, &option.column_options).unwrap();
+        assert_eq!(spec, imported.spec);
+        assert!(imported.warnings.is_empty());
+    }
+
+    #[test]
+    fn unknown_columns_are_skipped() {
+        let option = GenerateOption::default().column_options;
+        let header = |col, text: &str| calamine::Cell::new((0, col), Data::String(text.into()));
+        let sheet = Sheet {
+            range: Range::from_sparse(vec![
+                header(0, option.no_column.header),
+                header(1, "Owner"),
+                header(2, option.item_columns[0].header),
+            ]),
+            merged: Vec::new(),
+        };
+        let mut warnings = Vec::new();
+        let columns = header_columns(&sheet, &option, &mut warnings).unwrap();
+
+        assert_eq!(vec![(Column::No, 0), (Column::Item(0), 2)], columns);
+        assert_eq!(
+            vec!["skipped column B with the unknown header `Owner`"],
+            warnings
+        );
+    }
+
+    #[test]
+    fn step_data() {
+        assert_eq!(
+            ("Log in".to_string(), Some("alice (admin)".to_string())),
+            split_data("Log in (alice (admin))")
+        );
+        assert_eq!(("(Optional)".to_string(), None), split_data("(Optional)"));
+        assert_eq!(("Log in".to_string(), None), split_data("Log in"));
+        assert_eq!("AB", column_name(27));
+    }
+
+    #[test]
+    fn confirmations_and_steps() {
+        let mut case = Item::default();
+        let sheet = Sheet {
+            range: Range::from_sparse(vec![
+                calamine::Cell::new((0, 0), Data::String("1. Open\n2. Press".to_string())),
+                calamine::Cell::new((0, 1), Data::String("- Shown\n2. Done".to_string())),
+                calamine::Cell::new((0, 2), Data::String("passed".to_string())),
+            ]),
+            merged: Vec::new(),
+        };
+        let columns = [
+            (Column::Operations, 0),
+            (Column::Confirmations, 1),
+            (Column::Result, 2),
+        ];
+        read_case(&sheet, 0, &columns, &mut case).unwrap();
+
+        assert_eq!(vec!["Open"], case.operations);
+        assert_eq!(vec!["Shown"], case.confirmations);
+        assert_eq!(
+            vec![Step {
+                action: "Press".to_string(),
+                expected: Some("Done".to_string()),
+                data: None,
+            }],
+            case.steps
+        );
+        assert_eq!(Some(Status::Pass), case.result.map(|r| r.status));
+    }
+}
@@ -0,0 +1,575 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{bail, ensure, Context};
+use regex::Regex;
+
+use crate::testspec::{Item, Step, TestSpec};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Keyword {
+    Given,
+    When,
+    Then,
+}
+
+#[derive(Debug)]
+struct GherkinStep {
+    keyword: Keyword,
+    text: String,
+    /// Doc string or data table lines following the step.
+    data: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct Scenario {
+    item: Item,
+    outline: bool,
+    description: Vec<String>,
+    steps: Vec<GherkinStep>,
+    examples: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Block {
+    Description,
+    Background,
+    Scenario,
+    Examples,
+}
+
+/// Parses Gherkin feature files into a spec titled `title`: `Feature`s become primary items,
+/// `Rule`s secondary items and scenarios test cases. `Given` steps are read as
+/// preconditions, `When` as operations and `Then` as confirmations. When `When` steps follow
+/// a `Then`, or carry a doc string or data table, the `When`/`Then` pairs are read as steps
+/// instead. Scenario outlines keep their `Examples` with `<name>` replaced by `{{name}}`.
+///
+/// When some features have rules, the scenarios of the others are put under a rule titled
+/// after their feature, so that every test case is on the third level.
+pub fn import_gherkin(s: &str, title: &str) -> anyhow::Result<TestSpec> {
+    let mut features: Vec<Item> = Vec::new();
+    // Indices of the features with rules.
+    let mut ruled = BTreeSet::new();
+    let mut in_rule = false;
+    let mut scenario: Option<Scenario> = None;
+    let mut block = Block::Description;
+    let mut tags: Vec<String> = Vec::new();
+    // Delimiter, indentation and lines of the open doc string.
+    let mut doc_string: Option<(String, usize, Vec<String>)> = None;
+
+    for (i, raw) in s.lines().enumerate() {
+        let line = raw.trim();
+        let context = || format!("line {}: {line}", i + 1);
+
+        if let Some((delimiter, indent, lines)) = &mut doc_string {
+            if line == delimiter.as_str() {
+                let text = lines.join("\n");
+                match scenario.as_mut().and_then(|s| s.steps.last_mut()) {
+                    Some(step) => step.data = Some(text),
+                    None => unreachable!(),
+                }
+                doc_string = None;
+            } else {
+                let unindented = match raw.get(..*indent) {
+                    Some(prefix) if prefix.trim().is_empty() => &raw[*indent..],
+                    _ => raw.trim_start(),
+                };
+                lines.push(unindented.to_string());
+            }
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('@') {
+            tags.extend(line.split_whitespace().map(|t| t.to_string()));
+            continue;
+        }
+
+        if let Some((keyword, text)) = line.split_once(':') {
+            let (keyword, text) = (keyword.trim(), text.trim());
+            match keyword {
+                "Feature" => {
+                    finish(&mut scenario, &mut features, in_rule).with_context(context)?;
+                    features.push(tagged_item(text, std::mem::take(&mut tags))?);
+                    in_rule = false;
+                    block = Block::Description;
+                    continue;
+                }
+                "Rule" => {
+                    finish(&mut scenario, &mut features, in_rule).with_context(context)?;
+                    let feature = match features.last_mut() {
+                        Some(feature) => feature,
+                        None => bail!("{}: rule outside a feature", context()),
+                    };
+                    ensure!(
+                        in_rule || feature.children.is_empty(),
+                        "{}: scenarios outside a rule cannot be mixed with rules",
+                        context()
+                    );
+                    feature
+                        .children
+                        .push(tagged_item(text, std::mem::take(&mut tags))?);
+                    ruled.insert(features.len() - 1);
+                    in_rule = true;
+                    block = Block::Description;
+                    continue;
+                }
+                "Background" => {
+                    finish(&mut scenario, &mut features, in_rule).with_context(context)?;
+                    tags.clear();
+                    block = Block::Background;
+                    continue;
+                }
+                "Scenario" | "Example" | "Scenario Outline" | "Scenario Template" => {
+                    finish(&mut scenario, &mut features, in_rule).with_context(context)?;
+                    ensure!(
+                        !features.is_empty(),
+                        "{}: scenario outside a feature",
+                        context()
+                    );
+                    scenario = Some(Scenario {
+                        item: tagged_item(text, std::mem::take(&mut tags))?,
+                        outline: keyword.starts_with("Scenario "),
+                        ..Default::default()
+                    });
+                    block = Block::Scenario;
+                    continue;
+                }
+                "Examples" | "Scenarios" => {
+                    tags.clear();
+                    ensure!(
+                        matches!(&scenario, Some(s) if s.examples.is_empty()),
+                        "{}: unexpected examples",
+                        context()
+                    );
+                    block = Block::Examples;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        if line.starts_with("\"\"\"") || line.starts_with("```") {
+            let delimiter = &line[..3];
+            ensure!(
+                block == Block::Scenario && matches!(&scenario, Some(s) if !s.steps.is_empty()),
+                "{}: doc string outside a step",
+                context()
+            );
+            let indent = raw.len() - raw.trim_start().len();
+            doc_string = Some((delimiter.to_string(), indent, Vec::new()));
+            continue;
+        }
+
+        if line.starts_with('|') {
+            let scenario = match (&mut scenario, block) {
+                (Some(scenario), Block::Scenario | Block::Examples) => scenario,
+                _ => bail!("{}: table outside a step or examples", context()),
+            };
+            if block == Block::Examples {
+                scenario.examples.push(table_row(line));
+                continue;
+            }
+            match scenario.steps.last_mut() {
+                Some(step) => match &mut step.data {
+                    Some(data) => {
+                        data.push('\n');
+                        data.push_str(line);
+                    }
+                    None => step.data = Some(line.to_string()),
+                },
+                None => bail!("{}: table outside a step", context()),
+            }
+            continue;
+        }
+
+        if let Some((keyword, text)) = step(line) {
+            let keyword = match (keyword, &scenario, block) {
+                (Some(keyword), _, _) => keyword,
+                (None, Some(s), Block::Scenario) => match s.steps.last() {
+                    Some(step) => step.keyword,
+                    None => Keyword::When,
+                },
+                _ => Keyword::Given,
+            };
+            match block {
+                Block::Background => {
+                    let feature = match features.last_mut() {
+                        Some(feature) => feature,
+                        None => bail!("{}: background outside a feature", context()),
+                    };
+                    let item = match (in_rule, feature.children.last_mut()) {
+                        (true, Some(rule)) => rule,
+                        _ => feature,
+                    };
+                    item.preconditions.push(text.to_string());
+                }
+                Block::Scenario => match &mut scenario {
+                    Some(scenario) => scenario.steps.push(GherkinStep {
+                        keyword,
+                        text: text.to_string(),
+                        data: None,
+                    }),
+                    None => unreachable!(),
+                },
+                _ => bail!("{}: step outside a scenario", context()),
+            }
+            continue;
+        }
+
+        match (&mut scenario, block) {
+            (Some(scenario), Block::Scenario) if scenario.steps.is_empty() => {
+                scenario.description.push(line.to_string());
+            }
+            // Feature and rule descriptions have no place in the spec.
+            (_, Block::Description) => {}
+            _ => bail!("{}: unexpected line", context()),
+        }
+    }
+    ensure!(doc_string.is_none(), "unterminated doc string");
+    ensure!(!features.is_empty(), "no feature found");
+    finish(&mut scenario, &mut features, in_rule)?;
+
+    let has_rules = !ruled.is_empty();
+    if has_rules {
+        for (i, feature) in features.iter_mut().enumerate() {
+            if !ruled.contains(&i) && !feature.children.is_empty() {
+                feature.children = vec![Item {
+                    title: feature.title.clone(),
+                    children: std::mem::take(&mut feature.children),
+                    ..Default::default()
+                }];
+            }
+        }
+    }
+
+    let spec = TestSpec {
+        title: title.to_string(),
+        depth: if has_rules { 3 } else { 2 },
+        config: None,
+        cases: features,
+    };
+    spec.validate()?;
+    Ok(spec)
+}
+
+/// Splits a step line into its keyword (`None` for `And`, `But` and `*`) and text.
+fn step(line: &str) -> Option<(Option<Keyword>, &str)> {
+    let (keyword, text) = line.split_once(' ')?;
+    let keyword = match keyword {
+        "Given" => Some(Keyword::Given),
+        "When" => Some(Keyword::When),
+        "Then" => Some(Keyword::Then),
+        "And" | "But" | "*" => None,
+        _ => return None,
+    };
+    Some((keyword, text.trim()))
+}
+
+/// An item titled `title`, with `@id:` and `@priority:` tags read into the ID and priority.
+fn tagged_item(title: &str, tags: Vec<String>) -> anyhow::Result<Item> {
+    let mut item = Item {
+        title: title.to_string(),
+        ..Default::default()
+    };
+    for tag in tags {
+        let tag = tag.trim_start_matches('@');
+        if let Some(id) = tag.strip_prefix("id:") {
+            item.id = Some(id.to_string());
+        } else if let Some(priority) = tag.strip_prefix("priority:") {
+            let priority = priority
+                .parse()
+                .with_context(|| format!("invalid priority tag `@{tag}`"))?;
+            item.priority = Some(priority);
+        } else {
+            item.tags.push(tag.to_string());
+        }
+    }
+    Ok(item)
+}
+
+/// Converts the open scenario into a test case and attaches it to the current feature or
+/// rule.
+fn finish(
+    scenario: &mut Option<Scenario>,
+    features: &mut [Item],
+    in_rule: bool,
+) -> anyhow::Result<()> {
+    let scenario = match scenario.take() {
+        Some(scenario) => scenario,
+        None => return Ok(()),
+    };
+    let case = scenario.into_item()?;
+    let feature = match features.last_mut() {
+        Some(feature) => feature,
+        None => unreachable!(),
+    };
+    match (in_rule, feature.children.last_mut()) {
+        (true, Some(rule)) => rule.children.push(case),
+        _ => feature.children.push(case),
+    }
+    Ok(())
+}
+
+impl Scenario {
+    fn into_item(self) -> anyhow::Result<Item> {
+        let mut item = self.item;
+
+        for line in self.description {
+            if let Some(value) = line.strip_prefix("Environment:") {
+                item.environment.push(value.trim().to_string());
+            } else if let Some(value) = line.strip_prefix("Test data:") {
+                item.test_data.push(value.trim().to_string());
+            } else {
+                item.remarks.push(line);
+            }
+        }
+
+        let mut seen_then = false;
+        let as_steps = self.steps.iter().any(|s| {
+            let interleaved = s.keyword == Keyword::When && seen_then;
+            seen_then |= s.keyword == Keyword::Then;
+            interleaved || (s.keyword != Keyword::Given && s.data.is_some())
+        });
+        for s in self.steps {
+            match (s.keyword, as_steps) {
+                (Keyword::Given, _) => item.preconditions.push(s.text),
+                (Keyword::When, false) => item.operations.push(s.text),
+                (Keyword::Then, false) => item.confirmations.push(s.text),
+                (Keyword::When, true) => item.steps.push(Step {
+                    action: s.text,
+                    expected: None,
+                    data: s.data,
+                }),
+                (Keyword::Then, true) => match item.steps.last_mut() {
+                    Some(Step {
+                        expected: expected @ None,
+                        ..
+                    }) => *expected = Some(s.text),
+                    Some(Step {
+                        expected: Some(expected),
+                        ..
+                    }) => {
+                        expected.push('\n');
+                        expected.push_str(&s.text);
+                    }
+                    None => item.confirmations.push(s.text),
+                },
+            }
+        }
+
+        if self.outline {
+            let mut rows = self.examples.into_iter();
+            let names = match rows.next() {
+                Some(names) => names,
+                None => bail!("scenario outline `{}` has no examples", item.title),
+            };
+            for row in rows {
+                ensure!(
+                    row.len() == names.len(),
+                    "examples row of `{}` has {} cells, expected {}",
+                    item.title,
+                    row.len(),
+                    names.len()
+                );
+                item.examples
+                    .push(names.iter().cloned().zip(row).collect::<BTreeMap<_, _>>());
+            }
+            placeholders(&mut item, &names);
+        }
+        Ok(item)
+    }
+}
+
+/// Replaces the Gherkin placeholders `<name>` of the example columns with `{{name}}`.
+fn placeholders(item: &mut Item, names: &[String]) {
+    let regex = match Regex::new(r"<([^<>]+)>") {
+        Ok(regex) => regex,
+        Err(_) => unreachable!(),
+    };
+    let replace = |s: &mut String| {
+        *s = regex
+            .replace_all(s, |c: &regex::Captures| {
+                if names.iter().any(|n| *n == c[1]) {
+                    format!("{{{{{}}}}}", &c[1])
+                } else {
+                    c[0].to_string()
+                }
+            })
+            .into_owned();
+    };
+
+    replace(&mut item.title);
+    for values in [
+        &mut item.environment,
+        &mut item.preconditions,
+        &mut item.test_data,
+        &mut item.operations,
+        &mut item.confirmations,
+        &mut item.remarks,
+    ] {
+        values.iter_mut().for_each(replace);
+    }
+    for step in item.steps.iter_mut() {
+        replace(&mut step.action);
+        [&mut step.expected, &mut step.data]
+            .into_iter()
+            .flatten()
+            .for_each(replace);
+    }
+}
+
+/// Cells of a `| a | b |` row, with `\|`, `\\` and `\n` unescaped.
+fn table_row(line: &str) -> Vec<String> {
+    let inner = line.trim().trim_start_matches('|');
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => cell.push('\n'),
+                Some(c) => cell.push(c),
+                None => cell.push('\\'),
+            },
+            '|' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            c => cell.push(c),
+        }
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::generate_gherkin;
+
+    #[test]
+    fn round_trip() {
+        let s = "
+title: Spec title
+
+cases:
+  - title: Login
+    tags: [auth]
+    children:
+      - title: Password
+        children:
+          - id: TC-1
+            title: Valid password
+            priority: 1
+            tags: [smoke]
+            environment:
+              - Chrome
+            remarks:
+              - Use a test account
+            preconditions:
+              - The user is registered
+            operations:
+              - Open the login page
+              - Enter the password
+            confirmations:
+              - The top page is shown
+          - title: Log in as {{role}}
+            examples:
+              - role: admin
+              - role: a|b
+            steps:
+              - action: Open the login page
+                expected: The login page is shown
+              - action: Enter the {{role}} password
+                data: hunter2
+                expected: The top page is shown
+";
+        let spec: TestSpec = s.parse().unwrap();
+        let gherkin = generate_gherkin(&spec).unwrap();
+        assert_eq!(
+            spec.cases,
+            import_gherkin(&gherkin, "Spec title").unwrap().cases
+        );
+    }
+
+    #[test]
+    fn keywords() {
+        let s = r#"
+# language: en
+Feature: Login
+  As a user I want to log in.
+
+  Background:
+    Given the user is registered
+
+  @smoke
+  Scenario: Valid password
+    When I open the login page
+    * I enter the password
+    Then the top page is shown
+    But no error is shown
+
+  Scenario Outline: Log in as <role>
+    When I log in as <role> with <unknown>
+      | user | password |
+    Then I see the <page>
+
+    Examples:
+      | role  | page  |
+      | admin | Admin |
+"#;
+        let spec = import_gherkin(s, "Spec title").unwrap();
+        assert_eq!(2, spec.depth);
+        let feature = &spec.cases[0];
+        assert_eq!(vec!["the user is registered"], feature.preconditions);
+
+        let valid = &feature.children[0];
+        assert_eq!(vec!["smoke"], valid.tags);
+        assert_eq!(
+            vec!["I open the login page", "I enter the password"],
+            valid.operations
+        );
+        assert_eq!(
+            vec!["the top page is shown", "no error is shown"],
+            valid.confirmations
+        );
+
+        let outline = &feature.children[1];
+        assert_eq!("Log in as {{role}}", outline.title);
+        assert_eq!(
+            "I log in as {{role}} with <unknown>",
+            outline.steps[0].action
+        );
+        assert_eq!(
+            Some("| user | password |"),
+            outline.steps[0].data.as_deref()
+        );
+        assert_eq!(
+            Some("I see the {{page}}"),
+            outline.steps[0].expected.as_deref()
+        );
+        assert_eq!("Admin", outline.examples[0]["page"]);
+    }
+
+    #[test]
+    fn features_with_and_without_rules() {
+        let s = "
+Feature: Login
+  Rule: Password
+    Scenario: Valid password
+
+Feature: Logout
+  Scenario: Log out
+";
+        let spec = import_gherkin(s, "Spec title").unwrap();
+        assert_eq!(3, spec.depth);
+        let logout = &spec.cases[1];
+        assert_eq!("Logout", logout.children[0].title);
+        assert_eq!("Log out", logout.children[0].children[0].title);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(import_gherkin("Scenario: Orphan\n", "Spec title").is_err());
+        let mixed = "Feature: F\n  Scenario: S\n  Rule: R\n";
+        assert!(import_gherkin(mixed, "Spec title").is_err());
+    }
+}
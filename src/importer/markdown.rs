@@ -0,0 +1,496 @@
+use anyhow::{bail, ensure, Context};
+
+use super::Imported;
+use crate::locale::Locale;
+use crate::testspec::{ExecutionResult, Item, Status, Step, TestSpec, DEFAULT_DEPTH};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Section {
+    Environment,
+    Preconditions,
+    TestData,
+    Parameters,
+    Examples,
+    Operations,
+    Confirmations,
+    Steps,
+    Remarks,
+    Result,
+}
+
+impl Section {
+    /// Reads a section heading written in any of the built-in locales.
+    fn from_label(label: &str) -> Option<Section> {
+        // Older versions wrote the singular.
+        if label == "Confirmation" {
+            return Some(Section::Confirmations);
+        }
+        Locale::builtins().find_map(|locale| {
+            let labels = locale.sections;
+            let section = match label {
+                l if l == labels.environment => Section::Environment,
+                l if l == labels.preconditions => Section::Preconditions,
+                l if l == labels.test_data => Section::TestData,
+                l if l == labels.parameters => Section::Parameters,
+                l if l == labels.examples => Section::Examples,
+                l if l == labels.operations => Section::Operations,
+                l if l == labels.confirmations => Section::Confirmations,
+                l if l == labels.steps => Section::Steps,
+                l if l == labels.remarks => Section::Remarks,
+                l if l == labels.result => Section::Result,
+                _ => return None,
+            };
+            Some(section)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum ResultField {
+    Status,
+    Operator,
+    Date,
+    Note,
+    Defect,
+}
+
+impl ResultField {
+    fn from_label(label: &str) -> Option<ResultField> {
+        Locale::builtins().find_map(|locale| {
+            let labels = locale.sections;
+            let field = match label {
+                l if l == labels.status => ResultField::Status,
+                l if l == labels.operator => ResultField::Operator,
+                l if l == labels.date => ResultField::Date,
+                l if l == labels.note => ResultField::Note,
+                l if l == labels.defect => ResultField::Defect,
+                _ => return None,
+            };
+            Some(field)
+        })
+    }
+}
+
+/// Parses Markdown in the layout of `generate_markdown` back into a spec.
+///
+/// Item and section headings share the same `#` levels, so a heading is read as a section
+/// only when its text is a section name and it is followed by content rather than another
+/// heading. Markdown stops at `######`, so items below the fifth level cannot be recovered.
+/// A case whose confirmation boxes are all checked is recorded as passed; one with only some of
+/// them checked and no status gets no result and a warning.
+pub fn import_markdown(s: &str) -> anyhow::Result<Imported> {
+    let lines: Vec<&str> = s.lines().collect();
+
+    let mut title = None;
+    let mut cases = Vec::new();
+    let mut stack: Vec<Case> = Vec::new();
+    let mut section = None;
+    let mut warnings = Vec::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+        let context = || format!("line {}: {line}", i + 1);
+
+        let (hashes, text) = match heading(line) {
+            Some(heading) => heading,
+            None => {
+                match (stack.last_mut(), section) {
+                    (Some(case), Some(section)) => {
+                        case.read_line(section, line).with_context(context)?
+                    }
+                    _ => bail!("{}: expected a heading", context()),
+                }
+                continue;
+            }
+        };
+
+        if hashes == 1 {
+            ensure!(
+                title.is_none(),
+                "{}: the spec has a second title",
+                context()
+            );
+            title = Some(text.to_string());
+            continue;
+        }
+
+        let next = lines[i + 1..].iter().find(|l| !l.trim().is_empty());
+        let next_is_content = matches!(next, Some(l) if heading(l).is_none());
+        if let Some(s) = Section::from_label(text) {
+            if !stack.is_empty() && hashes == (stack.len() + 2).min(6) && next_is_content {
+                section = Some(s);
+                continue;
+            }
+        }
+
+        let level = hashes - 1;
+        while stack.len() >= level {
+            close(&mut stack, &mut cases, &mut warnings)?;
+        }
+        ensure!(
+            stack.len() + 1 == level,
+            "{}: heading skips a level",
+            context()
+        );
+        stack.push(Case::new(text, i + 1));
+        section = None;
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut cases, &mut warnings)?;
+    }
+
+    let title = match title {
+        Some(title) => title,
+        None => bail!("missing `# title` heading"),
+    };
+    let depth = cases.iter().map(item_depth).max().unwrap_or(DEFAULT_DEPTH);
+    let spec = TestSpec {
+        title,
+        depth,
+        config: None,
+        cases,
+    };
+    spec.validate()?;
+    Ok(Imported { spec, warnings })
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let hashes = line.chars().take_while(|c| *c == '#').count();
+    let text = line[hashes..].strip_prefix(' ')?;
+    if hashes == 0 {
+        None
+    } else {
+        Some((hashes, text.trim()))
+    }
+}
+
+fn item_depth(item: &Item) -> usize {
+    1 + item.children.iter().map(item_depth).max().unwrap_or(0)
+}
+
+/// Pops the innermost item off `stack` and attaches it to its parent.
+fn close(
+    stack: &mut Vec<Case>,
+    cases: &mut Vec<Item>,
+    warnings: &mut Vec<String>,
+) -> anyhow::Result<()> {
+    let item = match stack.pop() {
+        Some(case) => case.finish(warnings)?,
+        None => unreachable!(),
+    };
+    match stack.last_mut() {
+        Some(parent) => parent.item.children.push(item),
+        None => cases.push(item),
+    }
+    Ok(())
+}
+
+/// An item being parsed, with the state its sections need until the item is complete.
+struct Case {
+    item: Item,
+    /// 1-based line of the heading.
+    line: usize,
+    checked: usize,
+    steps_with_data: Option<bool>,
+    example_names: Option<Vec<String>>,
+    status: Option<Status>,
+    result_fields: Vec<(ResultField, String)>,
+}
+
+impl Case {
+    fn new(text: &str, line: usize) -> Case {
+        let (id, title) = match text.strip_prefix('[').and_then(|t| t.split_once("] ")) {
+            Some((id, title)) => (Some(id.to_string()), title.to_string()),
+            None => (None, text.to_string()),
+        };
+        Case {
+            item: Item {
+                id,
+                title,
+                ..Default::default()
+            },
+            line,
+            checked: 0,
+            steps_with_data: None,
+            example_names: None,
+            status: None,
+            result_fields: Vec::new(),
+        }
+    }
+
+    fn read_line(&mut self, section: Section, line: &str) -> anyhow::Result<()> {
+        let item = &mut self.item;
+        match section {
+            Section::Environment => item.environment.push(bullet(line)?),
+            Section::Preconditions => item.preconditions.push(bullet(line)?),
+            Section::TestData => item.test_data.push(bullet(line)?),
+            Section::Remarks => item.remarks.push(bullet(line)?),
+            Section::Parameters => match bullet(line)?.split_once(": ") {
+                Some((name, values)) => {
+                    let values = values.split(", ").map(|v| v.trim().to_string()).collect();
+                    item.parameters.insert(name.to_string(), values);
+                }
+                None => bail!("expected `- name: value, value`"),
+            },
+            Section::Examples => {
+                let cells = table_row(line)?;
+                match &self.example_names {
+                    None => self.example_names = Some(cells),
+                    Some(_) if is_separator(&cells) => {}
+                    Some(names) => item.examples.push(
+                        names
+                            .iter()
+                            .cloned()
+                            .zip(cells)
+                            .filter(|(_, value)| !value.is_empty())
+                            .collect(),
+                    ),
+                }
+            }
+            Section::Operations => match line.split_once(". ") {
+                Some((n, text)) if n.parse::<usize>().is_ok() => {
+                    item.operations.push(text.trim().to_string())
+                }
+                _ => bail!("expected a numbered operation"),
+            },
+            Section::Confirmations => {
+                let (checked, text) = match line.get(..6).zip(line.get(6..)) {
+                    Some(("- [ ] ", text)) => (false, text),
+                    Some(("- [x] ", text)) | Some(("- [X] ", text)) => (true, text),
+                    _ => bail!("expected a `- [ ]` check box"),
+                };
+                if checked {
+                    self.checked += 1;
+                }
+                item.confirmations.push(text.trim().to_string());
+            }
+            Section::Steps => {
+                let cells = table_row(line)?;
+                match self.steps_with_data {
+                    None => self.steps_with_data = Some(cells.len() > 3),
+                    Some(_) if is_separator(&cells) => {}
+                    Some(with_data) => {
+                        let cell = |i: usize| cells.get(i).filter(|c| !c.is_empty()).cloned();
+                        item.steps.push(Step {
+                            action: cell(1).unwrap_or_default(),
+                            expected: cell(2),
+                            data: if with_data { cell(3) } else { None },
+                        });
+                    }
+                }
+            }
+            Section::Result => {
+                let (label, value) = match bullet(line)?.split_once(": ") {
+                    Some((label, value)) => (label.to_string(), value.trim().to_string()),
+                    None => bail!("expected `- Label: value`"),
+                };
+                match ResultField::from_label(&label) {
+                    Some(ResultField::Status) => self.status = Some(value.parse()?),
+                    Some(field) => self.result_fields.push((field, value)),
+                    None => bail!("unknown result field `{label}`"),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self, warnings: &mut Vec<String>) -> anyhow::Result<Item> {
+        let Case {
+            mut item,
+            line,
+            checked,
+            status,
+            result_fields,
+            ..
+        } = self;
+
+        let all_checked = checked > 0 && checked == item.confirmations.len();
+        if checked > 0 && !all_checked && status.is_none() {
+            warnings.push(format!(
+                "line {line}: `{}` has {checked} of {} confirmations checked and no status, so no result was recorded",
+                item.title,
+                item.confirmations.len()
+            ));
+        }
+        let status = match (all_checked, status) {
+            (true, _) => Status::Pass,
+            (false, Some(status)) => status,
+            (false, None) if result_fields.is_empty() => return Ok(item),
+            (false, None) => bail!("{}: the result has no status", item.title),
+        };
+
+        let mut result = ExecutionResult {
+            status,
+            operator: None,
+            date: None,
+            note: None,
+            defect: None,
+        };
+        for (field, value) in result_fields {
+            let field = match field {
+                ResultField::Operator => &mut result.operator,
+                ResultField::Date => &mut result.date,
+                ResultField::Note => &mut result.note,
+                ResultField::Defect => &mut result.defect,
+                ResultField::Status => unreachable!(),
+            };
+            *field = Some(value);
+        }
+        item.result = Some(result);
+        Ok(item)
+    }
+}
+
+fn bullet(line: &str) -> anyhow::Result<String> {
+    match line.strip_prefix("- ") {
+        Some(text) => Ok(text.trim().to_string()),
+        None => bail!("expected a `- ` list item"),
+    }
+}
+
+fn is_separator(cells: &[String]) -> bool {
+    cells.iter().all(|c| c.chars().all(|c| "-: ".contains(c)))
+}
+
+/// Splits a table row into its cells, undoing the escaping of `generate_markdown`.
+fn table_row(line: &str) -> anyhow::Result<Vec<String>> {
+    let inner = match line
+        .trim()
+        .strip_prefix('|')
+        .and_then(|l| l.strip_suffix('|'))
+    {
+        Some(inner) => inner,
+        None => bail!("expected a table row"),
+    };
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('|') => cell.push('|'),
+                Some(c) => {
+                    cell.push('\\');
+                    cell.push(c);
+                }
+                None => cell.push('\\'),
+            },
+            '|' => cells.push(std::mem::take(&mut cell)),
+            c => cell.push(c),
+        }
+    }
+    cells.push(cell);
+    Ok(cells
+        .into_iter()
+        .map(|c| c.trim().replace("<br>", "\n"))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::generator::{generate_markdown, GenerateOption};
+
+    #[test]
+    fn round_trip() {
+        let s = "
+title: Spec title
+
+cases:
+  - id: '1'
+    title: Primary 1
+    children:
+      - title: Remarks
+        children:
+          - title: Tertiary 1-1-1
+            environment:
+              - Chrome
+            operations:
+              - Open the login page
+            confirmations:
+              - The login page is shown
+            steps:
+              - action: Enter `a|b`
+                expected: |-
+                  Accepted
+                  without errors
+                data: hunter2
+              - action: Press the login button
+            remarks:
+              - Flaky
+            result:
+              status: pass
+              operator: Alice
+          - id: TC-2
+            title: Tertiary 1-1-2
+            parameters:
+              browser: [chrome, firefox]
+            examples:
+              - role: admin
+                locale: en
+              - role: guest
+            confirmations:
+              - Shown
+            result:
+              status: fail
+              defect: BUG-12
+      - title: Secondary 1-2
+";
+        let spec: TestSpec = s.parse().unwrap();
+        let markdown = generate_markdown(&spec, &GenerateOption::default()).unwrap();
+        assert_eq!(spec, import_markdown(&markdown).unwrap().spec);
+
+        for locale in Locale::builtins() {
+            let option = GenerateOption {
+                locale: &locale,
+                ..Default::default()
+            };
+            let markdown = generate_markdown(&spec, &option).unwrap();
+            assert_eq!(spec, import_markdown(&markdown).unwrap().spec);
+        }
+    }
+
+    #[test]
+    fn checked_boxes() {
+        let s = "# Spec title
+
+## Case 1
+
+### Confirmation
+
+- [x] The login page is shown
+- [X] The password field is empty
+
+## Case 2
+
+### Confirmation
+
+- [x] The login page is shown
+- [ ] The password field is empty
+";
+        let Imported { spec, warnings } = import_markdown(s).unwrap();
+        assert_eq!(1, spec.depth);
+        assert_eq!(
+            Some(Status::Pass),
+            spec.cases[0].result.as_ref().map(|r| r.status)
+        );
+        assert_eq!(None, spec.cases[1].result);
+        assert_eq!(
+            vec!["line 10: `Case 2` has 1 of 2 confirmations checked and no status, so no result was recorded"],
+            warnings
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(import_markdown("## Case without title").is_err());
+        assert!(import_markdown("# Spec title\n\n### Skipped level").is_err());
+        assert!(import_markdown("# Spec title\n\nSome prose").is_err());
+        assert!(import_markdown("# Spec title\n\n## Case\n\n### Operations\n\n- Open").is_err());
+    }
+}
@@ -0,0 +1,468 @@
+mod report;
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt;
+
+use regex::Regex;
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::testspec::{Item, TestSpec};
+
+pub use report::{report_json, report_sarif, report_text};
+
+/// A check of the linter, named by its kebab-case ID in config, suppressions and reports.
+#[derive(Debug, Clone, Copy, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    DuplicateTitle,
+    EmptyChildren,
+    MissingConfirmations,
+    OperationVerb,
+    TrailingWhitespace,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 5] = [
+        Rule::DuplicateTitle,
+        Rule::EmptyChildren,
+        Rule::MissingConfirmations,
+        Rule::OperationVerb,
+        Rule::TrailingWhitespace,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Rule::DuplicateTitle => "duplicate-title",
+            Rule::EmptyChildren => "empty-children",
+            Rule::MissingConfirmations => "missing-confirmations",
+            Rule::OperationVerb => "operation-verb",
+            Rule::TrailingWhitespace => "trailing-whitespace",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Rule::DuplicateTitle => "Items under the same parent should have distinct titles",
+            Rule::EmptyChildren => {
+                "Items above the test case level should have children, or their rows are left half empty"
+            }
+            Rule::MissingConfirmations => {
+                "Test cases should have confirmations or steps with an expected result"
+            }
+            Rule::OperationVerb => "Operations and step actions should start with a verb",
+            Rule::TrailingWhitespace => "Texts should not end lines with whitespace",
+        }
+    }
+
+    fn default_severity(self) -> Severity {
+        match self {
+            Rule::DuplicateTitle => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl JsonSchema for Rule {
+    fn schema_name() -> Cow<'static, str> {
+        "Rule".into()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        let rules: Vec<Value> = Rule::ALL
+            .iter()
+            .map(|rule| json!({ "const": rule.id(), "description": rule.description() }))
+            .collect();
+        json_schema!({ "oneOf": rules })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Deserialize, Eq, JsonSchema, Ord, PartialEq, PartialOrd, Serialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Off,
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Severity::Off => "off",
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        f.write_str(s)
+    }
+}
+
+/// The `lint` table of a config: the severity of each rule, `off` to disable it.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct LintConfig {
+    /// Severity of each rule; `error` fails `testspecgen lint`. `duplicate-title` defaults to
+    /// `error` and the other rules to `warning`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rules: BTreeMap<Rule, Severity>,
+
+    /// Words an operation may start with; without it, `operation-verb` only flags operations
+    /// that start with an article, a pronoun or an -ing form.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verbs: Option<Vec<String>>,
+}
+
+impl LintConfig {
+    /// Returns `self` with the rules and verbs it does not set taken from `other`.
+    pub fn or(mut self, other: LintConfig) -> LintConfig {
+        for (rule, severity) in other.rules {
+            self.rules.entry(rule).or_insert(severity);
+        }
+        LintConfig {
+            rules: self.rules,
+            verbs: self.verbs.or(other.verbs),
+        }
+    }
+
+    pub fn severity(&self, rule: Rule) -> Severity {
+        self.rules
+            .get(&rule)
+            .copied()
+            .unwrap_or_else(|| rule.default_severity())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub rule: Rule,
+    pub severity: Severity,
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+}
+
+/// Checks `spec` against the enabled rules. `files` maps the files the items were read from
+/// to their contents, which are searched for suppression comments.
+pub fn lint(
+    spec: &TestSpec,
+    config: &LintConfig,
+    files: &BTreeMap<String, String>,
+) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        config,
+        depth: spec.depth,
+        diagnostics: Vec::new(),
+    };
+    linter.items(&spec.cases, 1);
+
+    let suppressions: BTreeMap<&str, Suppressions> = files
+        .iter()
+        .map(|(file, s)| (&file[..], Suppressions::parse(s)))
+        .collect();
+    linter
+        .diagnostics
+        .into_iter()
+        .filter(|d| {
+            let suppressions = d.file.as_deref().and_then(|f| suppressions.get(f));
+            !matches!(suppressions, Some(s) if s.suppresses(d.rule, d.line))
+        })
+        .collect()
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    depth: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter<'_> {
+    fn items(&mut self, items: &[Item], level: usize) {
+        let mut titles = HashSet::new();
+        for item in items {
+            if !titles.insert(&item.title) {
+                self.report(
+                    Rule::DuplicateTitle,
+                    item,
+                    format!("another item at this level is titled `{}`", item.title),
+                );
+            }
+            self.item(item, level);
+        }
+    }
+
+    fn item(&mut self, item: &Item, level: usize) {
+        let texts = std::iter::once(("title", &item.title))
+            .chain(item.environment.iter().map(|s| ("environment", s)))
+            .chain(item.preconditions.iter().map(|s| ("precondition", s)))
+            .chain(item.test_data.iter().map(|s| ("test data", s)))
+            .chain(item.operations.iter().map(|s| ("operation", s)))
+            .chain(item.confirmations.iter().map(|s| ("confirmation", s)))
+            .chain(item.steps.iter().flat_map(|step| {
+                std::iter::once(("step", &step.action))
+                    .chain(step.expected.iter().map(|s| ("expected result", s)))
+                    .chain(step.data.iter().map(|s| ("step data", s)))
+            }))
+            .chain(item.remarks.iter().map(|s| ("remark", s)));
+        for (field, text) in texts {
+            if text.lines().any(|l| l != l.trim_end()) {
+                self.report(
+                    Rule::TrailingWhitespace,
+                    item,
+                    format!("{field} `{}` has trailing whitespace", text.trim()),
+                );
+            }
+        }
+
+        if level < self.depth {
+            if item.children.is_empty() {
+                self.report(
+                    Rule::EmptyChildren,
+                    item,
+                    format!(
+                        "`{}` is at level {level} but has no children, so its row has no test case",
+                        item.title
+                    ),
+                );
+            }
+            self.items(&item.children, level + 1);
+            return;
+        }
+
+        if item.confirmations.is_empty() && item.steps.iter().all(|s| s.expected.is_none()) {
+            self.report(
+                Rule::MissingConfirmations,
+                item,
+                format!("`{}` has no confirmations", item.title),
+            );
+        }
+        let actions = item
+            .operations
+            .iter()
+            .map(|s| ("operation", s))
+            .chain(item.steps.iter().map(|s| ("step", &s.action)));
+        for (i, (field, text)) in actions.enumerate() {
+            if !starts_with_verb(text, self.config.verbs.as_deref()) {
+                self.report(
+                    Rule::OperationVerb,
+                    item,
+                    format!(
+                        "{field} {} of `{}` does not start with a verb: `{}`",
+                        i + 1,
+                        item.title,
+                        text.trim()
+                    ),
+                );
+            }
+        }
+    }
+
+    fn report(&mut self, rule: Rule, item: &Item, message: String) {
+        let severity = self.config.severity(rule);
+        if severity == Severity::Off {
+            return;
+        }
+        self.diagnostics.push(Diagnostic {
+            rule,
+            severity,
+            message,
+            file: item.source.as_ref().map(|s| s.file.clone()),
+            line: item.source.as_ref().and_then(|s| s.line),
+        });
+    }
+}
+
+/// Words that start a description rather than an instruction.
+const NON_VERBS: [&str; 16] = [
+    "a", "an", "the", "this", "that", "these", "those", "it", "i", "we", "you", "he", "she",
+    "they", "user", "users",
+];
+
+fn starts_with_verb(text: &str, verbs: Option<&[String]>) -> bool {
+    let word = text
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .trim_matches(|c: char| !c.is_alphanumeric());
+    // Only words in Latin script can be told apart.
+    if !word.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return true;
+    }
+    match verbs {
+        Some(verbs) => verbs.iter().any(|v| v.eq_ignore_ascii_case(word)),
+        None => {
+            let word = word.to_ascii_lowercase();
+            let gerund = word.len() > 5 && word.ends_with("ing");
+            !(gerund || NON_VERBS.contains(&&word[..]))
+        }
+    }
+}
+
+/// The `# testspecgen:disable` comments of a file. A comment on the line of an item, or alone
+/// on the line above it, suppresses the listed rules for that item; `disable-file` suppresses
+/// them for the whole file. Without a list, every rule is suppressed.
+#[derive(Debug, Default)]
+struct Suppressions {
+    file: Option<BTreeSet<Rule>>,
+    lines: BTreeMap<usize, Option<BTreeSet<Rule>>>,
+}
+
+impl Suppressions {
+    fn parse(s: &str) -> Suppressions {
+        let re = match Regex::new(r"#\s*testspecgen:disable(-file)?(?:\s+([\w\s,-]*))?$") {
+            Ok(re) => re,
+            Err(_) => unreachable!(),
+        };
+        let mut suppressions = Suppressions::default();
+        let lines: Vec<&str> = s.lines().collect();
+        for (i, line) in lines.iter().enumerate() {
+            let captures = match re.captures(line.trim_end()) {
+                Some(captures) => captures,
+                None => continue,
+            };
+            let rules = captures.get(2).map(|m| {
+                m.as_str()
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter_map(|id| Rule::ALL.into_iter().find(|r| r.id() == id))
+                    .collect::<BTreeSet<_>>()
+            });
+            let rules = rules.filter(|r| !r.is_empty());
+            if captures.get(1).is_some() {
+                match (&mut suppressions.file, rules) {
+                    (Some(file), Some(rules)) => file.extend(rules),
+                    (file, None) => *file = Some(Rule::ALL.into()),
+                    (file, Some(rules)) => *file = Some(rules),
+                }
+                continue;
+            }
+            // A comment on its own line applies to the next line.
+            let target = if line.trim_start().starts_with('#') {
+                i + 2
+            } else {
+                i + 1
+            };
+            suppressions.lines.insert(target, rules);
+        }
+        suppressions
+    }
+
+    fn suppresses(&self, rule: Rule, line: Option<usize>) -> bool {
+        if matches!(&self.file, Some(rules) if rules.contains(&rule)) {
+            return true;
+        }
+        match line.and_then(|l| self.lines.get(&l)) {
+            Some(None) => true,
+            Some(Some(rules)) => rules.contains(&rule),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testspec::SpecFormat;
+
+    fn lint_str(s: &str, config: &LintConfig) -> Vec<(Rule, Option<usize>)> {
+        let spec = TestSpec::load(s, SpecFormat::Yaml, None).unwrap();
+        let files = BTreeMap::from([("<stdin>".to_string(), s.to_string())]);
+        lint(&spec, config, &files)
+            .into_iter()
+            .map(|d| (d.rule, d.line))
+            .collect()
+    }
+
+    #[test]
+    fn rules() {
+        let s = "title: Spec title
+depth: 2
+cases:
+  - title: Primary 1
+    children:
+      - title: Case 1
+        operations:
+          - Open the login page
+          - The user presses the button
+        confirmations:
+          - 'Shown '
+      - title: Case 1
+        operations:
+          - Clicking the button
+  - title: Primary 2
+  - title: Primary 3
+    children:
+      - title: Case 3
+        steps:
+          - action: Press the button
+            expected: Shown
+";
+        assert_eq!(
+            vec![
+                (Rule::TrailingWhitespace, Some(6)),
+                (Rule::OperationVerb, Some(6)),
+                (Rule::DuplicateTitle, Some(12)),
+                (Rule::MissingConfirmations, Some(12)),
+                (Rule::OperationVerb, Some(12)),
+                (Rule::EmptyChildren, Some(15)),
+            ],
+            lint_str(s, &LintConfig::default())
+        );
+
+        let config: LintConfig = toml::from_str(
+            r#"
+verbs = ["open", "press"]
+
+[rules]
+operation-verb = "error"
+missing-confirmations = "off"
+trailing-whitespace = "info"
+"#,
+        )
+        .unwrap();
+        let diagnostics = lint(
+            &TestSpec::load(s, SpecFormat::Yaml, None).unwrap(),
+            &config,
+            &BTreeMap::new(),
+        );
+        assert_eq!(5, diagnostics.len());
+        assert_eq!(Severity::Info, diagnostics[0].severity);
+        assert_eq!(Severity::Error, diagnostics[1].severity);
+        assert!(!diagnostics
+            .iter()
+            .any(|d| d.rule == Rule::MissingConfirmations));
+    }
+
+    #[test]
+    fn suppressions() {
+        let s = "# testspecgen:disable-file missing-confirmations
+title: Spec title
+depth: 2
+cases:
+  # testspecgen:disable
+  - title: Primary 1
+  - title: Primary 2
+    children:
+      - title: Case 1
+        operations:
+          - The page opens
+      - title: Case 1 # testspecgen:disable duplicate-title, operation-verb
+        operations:
+          - The page opens
+";
+        assert_eq!(
+            vec![(Rule::OperationVerb, Some(9))],
+            lint_str(s, &LintConfig::default())
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use serde_json::{json, Value};
+
+use super::{Diagnostic, Rule, Severity};
+
+/// One `file:line: severity: message [rule]` line per diagnostic.
+pub fn report_text(diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(|d| {
+            let location = match (&d.file, d.line) {
+                (Some(file), Some(line)) => format!("{file}:{line}: "),
+                (Some(file), None) => format!("{file}: "),
+                (None, _) => String::new(),
+            };
+            format!("{location}{}: {} [{}]\n", d.severity, d.message, d.rule)
+        })
+        .collect()
+}
+
+pub fn report_json(diagnostics: &[Diagnostic]) -> anyhow::Result<String> {
+    Ok(serde_json::to_string_pretty(diagnostics)? + "\n")
+}
+
+/// A [SARIF 2.1.0](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html) log, as
+/// read by code scanning tools.
+pub fn report_sarif(diagnostics: &[Diagnostic]) -> anyhow::Result<String> {
+    let rules: Vec<Value> = Rule::ALL
+        .iter()
+        .map(|rule| {
+            json!({
+                "id": rule.id(),
+                "shortDescription": { "text": rule.description() },
+            })
+        })
+        .collect();
+    let results: Vec<Value> = diagnostics
+        .iter()
+        .map(|d| {
+            let mut result = json!({
+                "ruleId": d.rule.id(),
+                "level": match d.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                    Severity::Info | Severity::Off => "note",
+                },
+                "message": { "text": d.message },
+            });
+            if let Some(file) = &d.file {
+                let mut location = json!({ "artifactLocation": { "uri": file } });
+                if let Some(line) = d.line {
+                    location["region"] = json!({ "startLine": line });
+                }
+                result["locations"] = json!([{ "physicalLocation": location }]);
+            }
+            result
+        })
+        .collect();
+
+    let log = json!({
+        "version": "2.1.0",
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    });
+    Ok(serde_json::to_string_pretty(&log)? + "\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports() {
+        let diagnostics = vec![Diagnostic {
+            rule: Rule::EmptyChildren,
+            severity: Severity::Warning,
+            message: "`Primary 2` has no children".to_string(),
+            file: Some("spec.yml".to_string()),
+            line: Some(15),
+        }];
+        assert_eq!(
+            "spec.yml:15: warning: `Primary 2` has no children [empty-children]\n",
+            report_text(&diagnostics)
+        );
+
+        let json: Value = serde_json::from_str(&report_json(&diagnostics).unwrap()).unwrap();
+        assert_eq!("empty-children", json[0]["rule"]);
+        assert_eq!("warning", json[0]["severity"]);
+
+        let sarif: Value = serde_json::from_str(&report_sarif(&diagnostics).unwrap()).unwrap();
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!("empty-children", result["ruleId"]);
+        assert_eq!(
+            15,
+            result["locations"][0]["physicalLocation"]["region"]["startLine"]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::path::Path;
+use std::sync::OnceLock;
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+/// The built-in locales, by name.
+const BUILTIN: [(&str, &str); 4] = [
+    ("en", include_str!("../locales/en.toml")),
+    ("ja", include_str!("../locales/ja.toml")),
+    ("zh", include_str!("../locales/zh.toml")),
+    ("ko", include_str!("../locales/ko.toml")),
+];
+
+/// The texts of generated output: column headers, section labels and sheet names.
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
+pub struct Locale {
+    pub columns: ColumnLabels,
+    pub sections: SectionLabels,
+    pub sheets: SheetLabels,
+}
+
+/// Default column headers of the Excel layout.
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
+pub struct ColumnLabels {
+    pub no: String,
+    pub primary_item: String,
+    pub secondary_item: String,
+    pub tertiary_item: String,
+    pub extra_items: Vec<String>,
+    pub operator: String,
+    pub result: String,
+    pub date: String,
+    pub note: String,
+    pub defect: String,
+    pub environment: String,
+    pub preconditions: String,
+    pub test_data: String,
+    pub parameters: String,
+    pub operations: String,
+    pub confirmations: String,
+    pub remarks: String,
+}
+
+/// Headings and labels of document output such as Markdown.
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
+pub struct SectionLabels {
+    pub environment: String,
+    pub preconditions: String,
+    pub test_data: String,
+    pub parameters: String,
+    pub examples: String,
+    pub operations: String,
+    pub confirmations: String,
+    pub steps: String,
+    pub remarks: String,
+    pub result: String,
+    pub action: String,
+    pub expected: String,
+    pub data: String,
+    pub status: String,
+    pub operator: String,
+    pub date: String,
+    pub note: String,
+    pub defect: String,
+    pub contents: String,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Serialize)]
+pub struct SheetLabels {
+    /// Name of the sheet when the spec title cannot be one.
+    pub cases: String,
+}
+
+impl Locale {
+    pub fn english() -> &'static Locale {
+        static ENGLISH: OnceLock<Locale> = OnceLock::new();
+        ENGLISH.get_or_init(|| match Locale::builtin("en") {
+            Some(locale) => locale,
+            None => unreachable!(),
+        })
+    }
+
+    pub fn builtin(name: &str) -> Option<Locale> {
+        let (_, s) = BUILTIN.iter().find(|(n, _)| *n == name)?;
+        match toml::from_str(s) {
+            Ok(locale) => Some(locale),
+            Err(e) => unreachable!("invalid built-in locale {name}: {e}"),
+        }
+    }
+
+    /// Every built-in locale, English first.
+    pub fn builtins() -> impl Iterator<Item = Locale> {
+        BUILTIN.iter().filter_map(|(name, _)| Locale::builtin(name))
+    }
+
+    /// Loads a built-in locale by name, or a locale file. A locale file only needs the texts
+    /// that differ from the built-in locale named by its `base` key, English by default.
+    pub fn load(name_or_path: &str) -> anyhow::Result<Locale> {
+        if let Some(locale) = Locale::builtin(name_or_path) {
+            return Ok(locale);
+        }
+        let path = Path::new(name_or_path);
+        if !path.is_file() {
+            let names: Vec<_> = BUILTIN.iter().map(|(n, _)| *n).collect();
+            bail!(
+                "unknown locale `{name_or_path}`: expected one of {} or a locale file",
+                names.join(", ")
+            );
+        }
+
+        let s = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {name_or_path}"))?;
+        let mut custom: toml::Table =
+            toml::from_str(&s).with_context(|| format!("failed to parse {name_or_path}"))?;
+        let base = match custom.remove("base") {
+            Some(toml::Value::String(base)) => base,
+            Some(_) => bail!("{name_or_path}: `base` must be a locale name"),
+            None => "en".to_string(),
+        };
+        let mut table: toml::Table = match BUILTIN.iter().find(|(n, _)| *n == base) {
+            Some((_, s)) => toml::from_str(s)?,
+            None => bail!("{name_or_path}: unknown base locale `{base}`"),
+        };
+        for (key, value) in custom {
+            match (table.get_mut(&key), value) {
+                (Some(toml::Value::Table(labels)), toml::Value::Table(custom)) => {
+                    labels.extend(custom)
+                }
+                (_, value) => {
+                    table.insert(key, value);
+                }
+            }
+        }
+        toml::Value::Table(table)
+            .try_into()
+            .with_context(|| format!("failed to parse {name_or_path}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn builtins() {
+        assert_eq!(4, Locale::builtins().count());
+        assert_eq!("Operations", Locale::english().columns.operations);
+        assert_eq!("手順", Locale::load("ja").unwrap().sections.operations);
+        assert!(Locale::load("fr").is_err());
+    }
+
+    #[test]
+    fn custom_file() {
+        let temp = mktemp::Temp::new_dir().unwrap();
+        let path = temp.join("client.toml");
+        fs::write(
+            &path,
+            "base = \"ja\"\n\n[columns]\noperations = \"操作手順\"\n",
+        )
+        .unwrap();
+        let locale = Locale::load(path.to_str().unwrap()).unwrap();
+        assert_eq!("操作手順", locale.columns.operations);
+        assert_eq!("確認項目", locale.columns.confirmations);
+        assert_eq!("テストケース", locale.sheets.cases);
+    }
+}
@@ -1,5 +1,9 @@
 mod cli;
+mod config;
 mod generator;
+mod importer;
+mod lint;
+mod locale;
 mod testspec;
 
 fn main() -> anyhow::Result<()> {
@@ -1,48 +1,195 @@
+mod comments;
+mod error;
+mod filter;
+mod format;
+mod id;
+mod include;
+mod nodes;
+mod parameters;
+mod results;
+mod schema;
+
+use std::collections::BTreeMap;
 use std::str::FromStr;
 
+use anyhow::{ensure, Error};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
-#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+use crate::config::Config;
+
+pub use filter::Filter;
+pub use format::SpecFormat;
+pub use id::IdScheme;
+pub use include::Source;
+pub use results::{ExecutionResult, Results, Status};
+
+pub const DEFAULT_DEPTH: usize = 3;
+
+/// A test spec: the items to test, grouped into levels, with the settings to render them.
+#[derive(Debug, Deserialize, JsonSchema, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+#[schemars(title = "Test spec")]
 pub struct TestSpec {
+    /// Title of the spec, used as the document title and sheet name.
+    #[schemars(example = &"Login")]
     pub title: String,
 
+    /// Number of item levels; items at this level are the test cases.
+    #[serde(default = "default_depth", skip_serializing_if = "is_default_depth")]
+    #[schemars(range(min = 1), extend("default" = DEFAULT_DEPTH))]
+    pub depth: usize,
+
+    /// Output settings that apply to this spec, over those of `testspecgen.toml`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<Config>,
+
+    /// Top-level items.
     #[serde(default)]
-    pub cases: Vec<PrimaryItem>,
+    pub cases: Vec<Item>,
 }
 
-impl FromStr for TestSpec {
-    type Err = serde_yaml::Error;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        serde_yaml::from_str(s)
+fn default_depth() -> usize {
+    DEFAULT_DEPTH
+}
+
+fn is_default_depth(depth: &usize) -> bool {
+    *depth == DEFAULT_DEPTH
+}
+
+impl TestSpec {
+    /// Checks the structure of a spec whose includes have been expanded.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.validate_items(true)
+    }
+
+    /// Checks the structure of the items of the spec; include entries are skipped unless
+    /// they should have been `expanded` already.
+    fn validate_items(&self, expanded: bool) -> anyhow::Result<()> {
+        ensure!(self.depth >= 1, "depth must be at least 1");
+        validate_items(&self.cases, "cases", 1, self.depth, expanded)
+    }
+
+    /// Returns every item in document order, parents before their children.
+    pub fn items(&self) -> Vec<&Item> {
+        let mut items = Vec::new();
+        collect_items(&self.cases, &mut items);
+        items
+    }
+
+    /// Pushes the environment, preconditions and test data of every item down to its
+    /// children, so that each test case carries its full context.
+    pub fn resolve_inheritance(&mut self) {
+        for item in self.cases.iter_mut() {
+            item.resolve_inheritance();
+        }
+    }
+
+    /// Returns the test cases, i.e. the items at level `depth`, in document order.
+    pub fn test_cases(&self) -> Vec<&Item> {
+        let mut cases = Vec::new();
+        collect_cases(&self.cases, 1, self.depth, &mut cases);
+        cases
+    }
+
+    /// Returns the number of items at each level, from level 1 to `depth`.
+    pub fn items_per_level(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.depth];
+        count_items(&self.cases, 0, &mut counts);
+        counts
+    }
+
+    /// Returns the test cases, i.e. the items at level `depth`, in document order.
+    pub fn test_cases_mut(&mut self) -> Vec<&mut Item> {
+        let mut cases = Vec::new();
+        collect_cases_mut(&mut self.cases, 1, self.depth, &mut cases);
+        cases
     }
 }
 
-#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
-pub struct PrimaryItem {
-    pub title: String,
+fn validate_items(
+    items: &[Item],
+    path: &str,
+    level: usize,
+    depth: usize,
+    expanded: bool,
+) -> anyhow::Result<()> {
+    for (i, item) in items.iter().enumerate() {
+        let path = format!("{path}[{i}]");
+        let label = item.label(&path);
+        if item.include.is_some() {
+            ensure!(!expanded, "{label} was not resolved");
+            continue;
+        }
+        ensure!(!item.title.is_empty(), "{label} has no title");
+        if level == depth {
+            ensure!(
+                item.children.is_empty(),
+                "{label} is a test case (level {depth}) and cannot have children"
+            );
+        } else {
+            ensure!(
+                !item.has_case_fields(),
+                "{label} is at level {level}, but only test cases (level {depth}) can have operations, confirmations, steps, remarks, parameters or a result"
+            );
+            validate_items(
+                &item.children,
+                &format!("{path}.children"),
+                level + 1,
+                depth,
+                expanded,
+            )?;
+        }
+    }
+    Ok(())
+}
 
-    #[serde(default)]
-    pub children: Vec<SecondaryItem>,
+fn collect_items<'a>(items: &'a [Item], all: &mut Vec<&'a Item>) {
+    for item in items {
+        all.push(item);
+        collect_items(&item.children, all);
+    }
 }
 
-impl FromStr for PrimaryItem {
-    type Err = serde_yaml::Error;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        serde_yaml::from_str(s)
+fn count_items(items: &[Item], level: usize, counts: &mut [usize]) {
+    for item in items {
+        counts[level] += 1;
+        if level + 1 < counts.len() {
+            count_items(&item.children, level + 1, counts);
+        }
     }
 }
 
-#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
-pub struct SecondaryItem {
-    pub title: String,
+fn collect_cases<'a>(items: &'a [Item], level: usize, depth: usize, cases: &mut Vec<&'a Item>) {
+    for item in items {
+        if level == depth {
+            cases.push(item);
+        } else {
+            collect_cases(&item.children, level + 1, depth, cases);
+        }
+    }
+}
 
-    pub children: Vec<TertiaryItem>,
+fn collect_cases_mut<'a>(
+    items: &'a mut [Item],
+    level: usize,
+    depth: usize,
+    cases: &mut Vec<&'a mut Item>,
+) {
+    for item in items {
+        if level == depth {
+            cases.push(item);
+        } else {
+            collect_cases_mut(&mut item.children, level + 1, depth, cases);
+        }
+    }
 }
 
-impl FromStr for SecondaryItem {
-    type Err = serde_yaml::Error;
+impl FromStr for TestSpec {
+    type Err = Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        serde_yaml::from_str(s)
+        TestSpec::parse_as(s, SpecFormat::Yaml, None)
     }
 }
 
@@ -52,21 +199,180 @@ pub type Confirmation = String;
 
 pub type Remark = String;
 
-#[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
-pub struct TertiaryItem {
+pub type Precondition = String;
+
+pub type TestData = String;
+
+pub type Environment = String;
+
+/// An operation paired with the result it should produce.
+#[derive(Debug, Clone, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    /// What to do.
+    #[schemars(example = &"Press the login button")]
+    pub action: String,
+
+    /// The result the action should produce.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(example = &"The dashboard is shown")]
+    pub expected: Option<String>,
+
+    /// Data the action uses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(example = &"hunter2")]
+    pub data: Option<String>,
+}
+
+/// A node of the spec tree. Items above `TestSpec::depth` group their children; items at that
+/// level are test cases and carry the operations, confirmations and remarks.
+#[derive(Debug, Clone, Default, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+#[schemars(transform = include_alone)]
+pub struct Item {
+    /// Path or glob of files whose items replace this entry, relative to this file; see
+    /// `TestSpec::load`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(example = &"login/*.yml")]
+    pub include: Option<String>,
+
+    /// Stable ID shown instead of the row number.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(example = &"LOGIN-001")]
+    pub id: Option<String>,
+
+    /// Title of the item.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub title: String,
 
-    #[serde(default)]
+    /// Tags matched by `--filter tag:<tag>`; inherited by children.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(example = json!(["smoke"]))]
+    pub tags: Vec<String>,
+
+    /// Priority matched by `--filter priority<=<n>`; inherited by children.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(example = 1)]
+    pub priority: Option<u32>,
+
+    /// Custom metadata matched by `--filter meta.<key>:<value>`; inherited by children.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schemars(example = json!({ "owner": "alice" }))]
+    pub meta: BTreeMap<String, String>,
+
+    /// Child items; required above the test case level.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Item>,
+
+    /// Environments the item runs in; inherited by children.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(example = json!(["Chrome"]))]
+    pub environment: Vec<Environment>,
+
+    /// Preconditions of the item; inherited by children.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(example = json!(["A registered account exists"]))]
+    pub preconditions: Vec<Precondition>,
+
+    /// Test data of the item; inherited by children.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(example = json!(["user@example.com"]))]
+    pub test_data: Vec<TestData>,
+
+    /// Values of each `{{name}}` placeholder; the case is expanded once per combination.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schemars(example = json!({ "browser": ["chrome", "firefox"] }))]
+    pub parameters: BTreeMap<String, Vec<String>>,
+
+    /// Rows of placeholder values, combined with every combination of `parameters`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(example = json!([{ "role": "admin" }, { "role": "guest" }]))]
+    pub examples: Vec<BTreeMap<String, String>>,
+
+    /// Operations of the test case.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(example = json!(["Open the login page"]))]
     pub operations: Vec<Operation>,
 
-    #[serde(default)]
+    /// What to confirm after the operations.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(example = json!(["The login page is shown"]))]
     pub confirmations: Vec<Confirmation>,
 
-    #[serde(default)]
+    /// Operations paired with the result they should produce.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<Step>,
+
+    /// Remarks on the test case.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(example = json!(["Flaky on Safari"]))]
     pub remarks: Vec<Remark>,
+
+    /// The outcome of the last run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<ExecutionResult>,
+
+    #[serde(skip)]
+    pub source: Option<Source>,
+}
+
+/// An include entry stands alone: it has `include` and no other field, while every other
+/// item has a `title`.
+fn include_alone(schema: &mut schemars::Schema) {
+    schema.insert(
+        "oneOf".to_string(),
+        json!([
+            { "required": ["include"], "maxProperties": 1 },
+            { "required": ["title"], "not": { "required": ["include"] } },
+        ]),
+    );
 }
 
-impl FromStr for TertiaryItem {
+impl Item {
+    fn resolve_inheritance(&mut self) {
+        if self.children.is_empty() {
+            return;
+        }
+        let environment = std::mem::take(&mut self.environment);
+        let preconditions = std::mem::take(&mut self.preconditions);
+        let test_data = std::mem::take(&mut self.test_data);
+        for child in self.children.iter_mut() {
+            prepend(&mut child.environment, &environment);
+            prepend(&mut child.preconditions, &preconditions);
+            prepend(&mut child.test_data, &test_data);
+            child.resolve_inheritance();
+        }
+    }
+
+    /// The quoted title and `path` in the tree, followed by where the item was read from
+    /// when known.
+    fn label(&self, path: &str) -> String {
+        let name = match &self.include {
+            Some(include) if self.title.is_empty() => format!("include `{include}`"),
+            _ => format!("`{}`", self.title),
+        };
+        match &self.source {
+            Some(source) => format!("{name} at `{path}` ({source})"),
+            None => format!("{name} at `{path}`"),
+        }
+    }
+
+    fn has_case_fields(&self) -> bool {
+        !(self.operations.is_empty()
+            && self.confirmations.is_empty()
+            && self.steps.is_empty()
+            && self.remarks.is_empty()
+            && self.parameters.is_empty()
+            && self.examples.is_empty()
+            && self.result.is_none())
+    }
+}
+
+fn prepend(values: &mut Vec<String>, inherited: &[String]) {
+    values.splice(0..0, inherited.iter().cloned());
+}
+
+impl FromStr for Item {
     type Err = serde_yaml::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         serde_yaml::from_str(s)
@@ -84,6 +390,8 @@ title: Spec title
 ";
         let expected = TestSpec {
             title: "Spec title".to_string(),
+            depth: 3,
+            config: None,
             cases: vec![],
         };
         let actual: TestSpec = s.parse().unwrap();
@@ -111,17 +419,22 @@ cases:
 
         let expected = TestSpec {
             title: "Spec title".to_string(),
-            cases: vec![PrimaryItem {
+            depth: 3,
+            config: None,
+            cases: vec![Item {
                 title: "Primary 1".to_string(),
-                children: vec![SecondaryItem {
+                children: vec![Item {
                     title: "Secondary 1-1".to_string(),
-                    children: vec![TertiaryItem {
+                    children: vec![Item {
                         title: "Tertiary 1-1-1".to_string(),
                         operations: vec!["Operation 1-1-1-1".to_string()],
                         confirmations: vec!["Confirmation 1-1-1-1".to_string()],
                         remarks: vec!["Remark 1-1-1-1".to_string()],
+                        ..Default::default()
                     }],
+                    ..Default::default()
                 }],
+                ..Default::default()
             }],
         };
         let actual: TestSpec = s.parse().unwrap();
@@ -237,14 +550,16 @@ cases:
 
         let expected = TestSpec {
             title: "Spec title".to_string(),
+            depth: 3,
+            config: None,
             cases: vec![
-                PrimaryItem {
+                Item {
                     title: "Primary 1".to_string(),
                     children: vec![
-                        SecondaryItem {
+                        Item {
                             title: "Secondary 1-1".to_string(),
                             children: vec![
-                                TertiaryItem {
+                                Item {
                                     title: "Tertiary 1-1-1".to_string(),
                                     operations: vec![
                                         "Operation 1-1-1-1".to_string(),
@@ -258,8 +573,9 @@ cases:
                                         "Remark 1-1-1-1".to_string(),
                                         "Remark 1-1-1-2".to_string(),
                                     ],
+                                    ..Default::default()
                                 },
-                                TertiaryItem {
+                                Item {
                                     title: "Tertiary 1-1-2".to_string(),
                                     operations: vec![
                                         "Operation 1-1-2-1".to_string(),
@@ -273,13 +589,15 @@ cases:
                                         "Remark 1-1-2-1".to_string(),
                                         "Remark 1-1-2-2".to_string(),
                                     ],
+                                    ..Default::default()
                                 },
                             ],
+                            ..Default::default()
                         },
-                        SecondaryItem {
+                        Item {
                             title: "Secondary 1-2".to_string(),
                             children: vec![
-                                TertiaryItem {
+                                Item {
                                     title: "Tertiary 1-2-1".to_string(),
                                     operations: vec![
                                         "Operation 1-2-1-1".to_string(),
@@ -293,8 +611,9 @@ cases:
                                         "Remark 1-2-1-1".to_string(),
                                         "Remark 1-2-1-2".to_string(),
                                     ],
+                                    ..Default::default()
                                 },
-                                TertiaryItem {
+                                Item {
                                     title: "Tertiary 1-2-2".to_string(),
                                     operations: vec![
                                         "Operation 1-2-2-1".to_string(),
@@ -308,18 +627,21 @@ cases:
                                         "Remark 1-2-2-1".to_string(),
                                         "Remark 1-2-2-2".to_string(),
                                     ],
+                                    ..Default::default()
                                 },
                             ],
+                            ..Default::default()
                         },
                     ],
+                    ..Default::default()
                 },
-                PrimaryItem {
+                Item {
                     title: "Primary 2".to_string(),
                     children: vec![
-                        SecondaryItem {
+                        Item {
                             title: "Secondary 2-1".to_string(),
                             children: vec![
-                                TertiaryItem {
+                                Item {
                                     title: "Tertiary 2-1-1".to_string(),
                                     operations: vec![
                                         "Operation 2-1-1-1".to_string(),
@@ -333,8 +655,9 @@ cases:
                                         "Remark 2-1-1-1".to_string(),
                                         "Remark 2-1-1-2".to_string(),
                                     ],
+                                    ..Default::default()
                                 },
-                                TertiaryItem {
+                                Item {
                                     title: "Tertiary 2-1-2".to_string(),
                                     operations: vec![
                                         "Operation 2-1-2-1".to_string(),
@@ -348,13 +671,15 @@ cases:
                                         "Remark 2-1-2-1".to_string(),
                                         "Remark 2-1-2-2".to_string(),
                                     ],
+                                    ..Default::default()
                                 },
                             ],
+                            ..Default::default()
                         },
-                        SecondaryItem {
+                        Item {
                             title: "Secondary 2-2".to_string(),
                             children: vec![
-                                TertiaryItem {
+                                Item {
                                     title: "Tertiary 2-2-1".to_string(),
                                     operations: vec![
                                         "Operation 2-2-1-1".to_string(),
@@ -368,8 +693,9 @@ cases:
                                         "Remark 2-2-1-1".to_string(),
                                         "Remark 2-2-1-2".to_string(),
                                     ],
+                                    ..Default::default()
                                 },
-                                TertiaryItem {
+                                Item {
                                     title: "Tertiary 2-2-2".to_string(),
                                     operations: vec![
                                         "Operation 2-2-2-1".to_string(),
@@ -383,14 +709,191 @@ cases:
                                         "Remark 2-2-2-1".to_string(),
                                         "Remark 2-2-2-2".to_string(),
                                     ],
+                                    ..Default::default()
                                 },
                             ],
+                            ..Default::default()
                         },
                     ],
+                    ..Default::default()
                 },
             ],
         };
         let actual: TestSpec = s.parse().unwrap();
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn two_levels() {
+        let s = "
+title: Spec title
+depth: 2
+
+cases:
+  - title: Primary 1
+    children:
+      - title: Case 1-1
+        operations:
+          - Operation 1-1-1
+";
+
+        let expected = TestSpec {
+            title: "Spec title".to_string(),
+            depth: 2,
+            config: None,
+            cases: vec![Item {
+                title: "Primary 1".to_string(),
+                children: vec![Item {
+                    title: "Case 1-1".to_string(),
+                    operations: vec!["Operation 1-1-1".to_string()],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        };
+        let mut actual: TestSpec = s.parse().unwrap();
+        assert_eq!(expected, actual);
+        assert_eq!(1, actual.test_cases_mut().len());
+    }
+
+    #[test]
+    fn five_levels() {
+        let s = "
+title: Spec title
+depth: 5
+
+cases:
+  - title: Level 1
+    children:
+      - title: Level 2
+        children:
+          - title: Level 3
+            children:
+              - title: Level 4
+                children:
+                  - title: Case 1
+                  - title: Case 2
+          - title: Empty level 3
+";
+
+        let mut spec: TestSpec = s.parse().unwrap();
+        let titles: Vec<_> = spec
+            .test_cases_mut()
+            .into_iter()
+            .map(|c| &c.title[..])
+            .collect();
+        assert_eq!(vec!["Case 1", "Case 2"], titles);
+        assert_eq!(vec![1, 1, 2, 1, 2], spec.items_per_level());
+    }
+
+    #[test]
+    fn steps() {
+        let s = "
+title: Spec title
+depth: 1
+
+cases:
+  - title: Case 1
+    operations:
+      - Open the login page
+    steps:
+      - action: Enter the password
+        expected: The password is masked
+        data: hunter2
+      - action: Press the login button
+";
+
+        let expected = TestSpec {
+            title: "Spec title".to_string(),
+            depth: 1,
+            config: None,
+            cases: vec![Item {
+                title: "Case 1".to_string(),
+                operations: vec!["Open the login page".to_string()],
+                steps: vec![
+                    Step {
+                        action: "Enter the password".to_string(),
+                        expected: Some("The password is masked".to_string()),
+                        data: Some("hunter2".to_string()),
+                    },
+                    Step {
+                        action: "Press the login button".to_string(),
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }],
+        };
+        let actual: TestSpec = s.parse().unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn inheritance() {
+        let s = "
+title: Spec title
+depth: 2
+
+cases:
+  - title: Primary 1
+    environment:
+      - Chrome
+    preconditions:
+      - Logged out
+    children:
+      - title: Case 1-1
+        preconditions:
+          - On the login page
+        test_data:
+          - user@example.com
+      - title: Case 1-2
+";
+
+        let mut spec: TestSpec = s.parse().unwrap();
+        spec.resolve_inheritance();
+
+        let primary = &spec.cases[0];
+        assert!(primary.environment.is_empty());
+        assert!(primary.preconditions.is_empty());
+
+        let case = &primary.children[0];
+        assert_eq!(vec!["Chrome"], case.environment);
+        assert_eq!(vec!["Logged out", "On the login page"], case.preconditions);
+        assert_eq!(vec!["user@example.com"], case.test_data);
+
+        let case = &primary.children[1];
+        assert_eq!(vec!["Logged out"], case.preconditions);
+        assert!(case.test_data.is_empty());
+    }
+
+    #[test]
+    fn case_with_children() {
+        let s = "
+title: Spec title
+depth: 2
+
+cases:
+  - title: Primary 1
+    children:
+      - title: Case 1-1
+        children:
+          - title: Too deep
+";
+
+        assert!(s.parse::<TestSpec>().is_err());
+    }
+
+    #[test]
+    fn category_with_operations() {
+        let s = "
+title: Spec title
+
+cases:
+  - title: Primary 1
+    operations:
+      - Operation 1
+";
+
+        assert!(s.parse::<TestSpec>().is_err());
+    }
 }
@@ -0,0 +1,453 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use anyhow::bail;
+use yaml_rust2::scanner::TScalarStyle;
+
+use super::nodes::{nodes, Node};
+use super::{SpecFormat, TestSpec};
+
+impl TestSpec {
+    /// Writes the spec as `format` with the comments of `original`, the text it was read from.
+    /// In YAML each comment stays with the value it is on or above, and a run of blank lines
+    /// before a value becomes one blank line; TOML keeps only the comments at the top of the
+    /// file and refuses to drop any other.
+    pub fn to_string_keeping_comments(
+        &self,
+        format: SpecFormat,
+        original: &str,
+    ) -> anyhow::Result<String> {
+        keep_comments(format, original, &self.to_string_as(format)?)
+    }
+}
+
+/// Adds the comments of `original` to `s`, the value read from it written back as `format`.
+pub(super) fn keep_comments(format: SpecFormat, original: &str, s: &str) -> anyhow::Result<String> {
+    match format {
+        SpecFormat::Yaml => keep_yaml_comments(original, s),
+        SpecFormat::Json => Ok(s.to_string()),
+        SpecFormat::Toml => keep_toml_header(original, s),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Comment {
+    /// 1-based line and column of the `#`.
+    position: (usize, usize),
+    /// From the `#` to the end of the line.
+    text: String,
+    /// Whether the comment is alone on its line.
+    own_line: bool,
+    blank_after: bool,
+}
+
+/// Puts each comment of `original` above or after the line of the same value in `s`, and a
+/// blank line above each value that followed blank lines.
+fn keep_yaml_comments(original: &str, s: &str) -> anyhow::Result<String> {
+    let before = nodes(original);
+    let comments = yaml_comments(original, &before);
+    let blank_lines = blank_lines(original, &before);
+    if comments.is_empty() && blank_lines.is_empty() {
+        return Ok(s.to_string());
+    }
+
+    let mut lines: HashMap<(&str, bool), usize> = HashMap::new();
+    let after = nodes(s);
+    for node in &after {
+        lines
+            .entry((&node.path, node.key))
+            .or_insert(node.position.0);
+    }
+    let line_of = |node: &Node| lines.get(&(&node.path[..], node.key)).copied();
+
+    let mut above: BTreeMap<usize, Vec<&Comment>> = BTreeMap::new();
+    let mut trailing: BTreeMap<usize, Vec<&Comment>> = BTreeMap::new();
+    let mut end = Vec::new();
+    for comment in &comments {
+        let (line, column) = comment.position;
+        if comment.own_line {
+            // Above the next value.
+            match before
+                .iter()
+                .filter(|n| n.position.0 > line)
+                .find_map(line_of)
+            {
+                Some(l) => above.entry(l).or_default().push(comment),
+                None => end.push(comment),
+            }
+        } else {
+            // After the first value on its line, else the last one before it.
+            let on_line = before
+                .iter()
+                .filter(|n| n.position.0 == line && n.position.1 < column);
+            let earlier = before.iter().rev().filter(|n| n.position < (line, column));
+            match on_line.chain(earlier).find_map(line_of) {
+                Some(l) => trailing.entry(l).or_default().push(comment),
+                None => end.push(comment),
+            }
+        }
+    }
+
+    // Above the next value.
+    let blank_above: BTreeSet<usize> = blank_lines
+        .into_iter()
+        .filter_map(|line| {
+            before
+                .iter()
+                .filter(|n| n.position.0 > line)
+                .find_map(line_of)
+        })
+        .collect();
+
+    let mut buf = String::new();
+    for (i, l) in s.lines().enumerate() {
+        let indent = &l[..l.len() - l.trim_start().len()];
+        if i > 0 && blank_above.contains(&(i + 1)) {
+            buf.push('\n');
+        }
+        for comment in above.get(&(i + 1)).into_iter().flatten() {
+            buf.push_str(&format!("{indent}{}\n", comment.text));
+            if comment.blank_after {
+                buf.push('\n');
+            }
+        }
+        buf.push_str(l);
+        for comment in trailing.get(&(i + 1)).into_iter().flatten() {
+            buf.push_str(&format!(" {}", comment.text));
+        }
+        buf.push('\n');
+    }
+    for comment in end {
+        let indent = " ".repeat(comment.position.1 - 1);
+        buf.push_str(&format!("{indent}{}\n", comment.text));
+    }
+
+    // A comment placed where YAML does not allow one would change the spec or be lost.
+    let mut kept: Vec<_> = yaml_comments(&buf, &nodes(&buf))
+        .into_iter()
+        .map(|c| c.text)
+        .collect();
+    kept.sort();
+    for comment in &comments {
+        match kept.binary_search(&comment.text) {
+            Ok(i) => {
+                kept.remove(i);
+            }
+            Err(_) => bail!(
+                "cannot keep the comment at line {}: {}",
+                comment.position.0,
+                comment.text
+            ),
+        }
+    }
+    Ok(buf)
+}
+
+/// Returns the first line of each run of blank lines that follows a value, leaving out those
+/// inside block scalars and those after a comment, which [`Comment::blank_after`] covers.
+/// Blank lines at the end of a block scalar are not part of its value, so they count.
+fn blank_lines(s: &str, nodes: &[Node]) -> Vec<usize> {
+    let content = block_content_lines(s, nodes);
+    let lines: Vec<&str> = s.lines().collect();
+    let inside_block = |i: usize| {
+        let next = (i..lines.len()).find(|&j| !lines[j].trim().is_empty());
+        content.contains(&(i + 1)) && next.is_some_and(|j| content.contains(&(j + 1)))
+    };
+    (1..lines.len())
+        .filter(|&i| lines[i].trim().is_empty() && !inside_block(i))
+        .filter(|&i| {
+            let previous = lines[i - 1].trim();
+            !previous.is_empty() && !previous.starts_with('#')
+        })
+        .map(|i| i + 1)
+        .collect()
+}
+
+/// Returns the lines holding the content of a block scalar.
+fn block_content_lines(s: &str, nodes: &[Node]) -> HashSet<usize> {
+    // The first line, indentation and end of the content of each block scalar.
+    let blocks: Vec<(usize, usize, (usize, usize))> = nodes
+        .iter()
+        .filter_map(|n| Some((n.position.0, n.position.1 - 1, n.end?)))
+        .collect();
+    s.lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l))
+        .filter(|&(line, l)| {
+            let indent = l.len() - l.trim_start().len();
+            blocks.iter().any(|&(first, block_indent, end)| {
+                (first..=end.0).contains(&line)
+                    && (line, indent + 1) < end
+                    && (l.trim().is_empty() || indent >= block_indent)
+            })
+        })
+        .map(|(line, _)| line)
+        .collect()
+}
+
+/// Returns the comments of a YAML or JSON document, skipping `#` in quoted and block scalars.
+fn yaml_comments(s: &str, nodes: &[Node]) -> Vec<Comment> {
+    let quotes: HashMap<(usize, usize), char> = nodes
+        .iter()
+        .filter_map(|n| match n.style {
+            Some(TScalarStyle::SingleQuoted) => Some((n.position, '\'')),
+            Some(TScalarStyle::DoubleQuoted) => Some((n.position, '"')),
+            _ => None,
+        })
+        .collect();
+    let content = block_content_lines(s, nodes);
+
+    let lines: Vec<&str> = s.lines().collect();
+    let mut comments = Vec::new();
+    let mut quote: Option<char> = None;
+    for (i, l) in lines.iter().enumerate() {
+        let line = i + 1;
+        if content.contains(&line) && quote.is_none() {
+            continue;
+        }
+
+        let chars: Vec<(usize, char)> = l.char_indices().collect();
+        let mut j = 0;
+        while j < chars.len() {
+            let (offset, c) = chars[j];
+            match quote {
+                Some('"') if c == '\\' => j += 1,
+                Some('\'') if c == '\'' && matches!(chars.get(j + 1), Some((_, '\''))) => j += 1,
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None if quotes.contains_key(&(line, j + 1)) => {
+                    quote = quotes.get(&(line, j + 1)).copied()
+                }
+                None if c == '#' && (j == 0 || chars[j - 1].1.is_whitespace()) => {
+                    comments.push(Comment {
+                        position: (line, j + 1),
+                        text: l[offset..].trim_end().to_string(),
+                        own_line: l[..offset].trim().is_empty(),
+                        blank_after: lines.get(i + 1).is_some_and(|l| l.trim().is_empty()),
+                    });
+                    break;
+                }
+                None => {}
+            }
+            j += 1;
+        }
+    }
+    comments
+}
+
+/// Keeps the comment lines at the top of `original`, and fails if it has any other comment.
+fn keep_toml_header(original: &str, s: &str) -> anyhow::Result<String> {
+    let header: Vec<&str> = original
+        .lines()
+        .take_while(|l| l.starts_with('#'))
+        .collect();
+    if let Some(line) = toml_comment_lines(original)
+        .into_iter()
+        .find(|&l| l > header.len())
+    {
+        bail!(
+            "cannot keep the comment at line {line}; TOML specs keep only the comments at the top"
+        );
+    }
+
+    let mut buf: String = header.iter().map(|l| format!("{l}\n")).collect();
+    if !buf.is_empty() {
+        buf.push('\n');
+    }
+    buf.push_str(s);
+    Ok(buf)
+}
+
+/// Returns the lines of the comments of a TOML document.
+fn toml_comment_lines(s: &str) -> Vec<usize> {
+    let chars: Vec<char> = s.chars().collect();
+    let starts_with = |i: usize, pattern: &str| {
+        pattern
+            .chars()
+            .enumerate()
+            .all(|(k, p)| chars.get(i + k) == Some(&p))
+    };
+
+    let mut lines = Vec::new();
+    let mut line = 1;
+    // The delimiter of the string being read.
+    let mut string: Option<&str> = None;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line += 1;
+        }
+        match string {
+            Some(delimiter) if delimiter.starts_with('"') && c == '\\' => i += 1,
+            Some(delimiter) if starts_with(i, delimiter) => {
+                i += delimiter.len() - 1;
+                string = None;
+            }
+            Some(_) => {}
+            None if c == '#' => {
+                lines.push(line);
+                while i + 1 < chars.len() && chars[i + 1] != '\n' {
+                    i += 1;
+                }
+            }
+            None => {
+                string = ["\"\"\"", "'''", "\"", "'"]
+                    .into_iter()
+                    .find(|d| starts_with(i, d));
+                if let Some(delimiter) = string {
+                    i += delimiter.len() - 1;
+                }
+            }
+        }
+        i += 1;
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yaml_comments_are_kept() {
+        let s = "# yaml-language-server: $schema=schema.json
+
+title: Spec title # the title
+depth: 1
+cases:
+    # testspecgen:disable missing-confirmations
+    - title: Case 1
+      operations: [ 'Open # page' ]
+      remarks:
+        - |
+          # not a comment
+        # about the remarks
+        - Two
+    - title: Case 2   # testspecgen:disable duplicate-title
+      operations:
+        - \"Open # page\" # trailing
+# at the end
+";
+        let mut spec = TestSpec::parse_as(s, SpecFormat::Yaml, None).unwrap();
+        let formatted = spec
+            .to_string_keeping_comments(SpecFormat::Yaml, s)
+            .unwrap();
+        assert_eq!(
+            "# yaml-language-server: $schema=schema.json
+
+title: Spec title # the title
+depth: 1
+cases:
+# testspecgen:disable missing-confirmations
+- title: Case 1
+  operations:
+  - 'Open # page'
+  remarks:
+  - |
+    # not a comment
+  # about the remarks
+  - Two
+- title: Case 2 # testspecgen:disable duplicate-title
+  operations:
+  - 'Open # page' # trailing
+# at the end
+",
+            formatted
+        );
+        assert_eq!(
+            formatted,
+            spec.to_string_keeping_comments(SpecFormat::Yaml, &formatted)
+                .unwrap()
+        );
+
+        spec.cases[1].id = Some("2".to_string());
+        let with_id = spec
+            .to_string_keeping_comments(SpecFormat::Yaml, s)
+            .unwrap();
+        assert!(
+            with_id.contains("- id: '2' # testspecgen:disable duplicate-title\n  title: Case 2\n")
+        );
+    }
+
+    #[test]
+    fn blank_lines_are_kept() {
+        let s = "title: Spec title
+depth: 1
+
+cases:
+    - title: Case 1
+      remarks:
+        - |
+          One
+
+          Two
+
+
+    - title: Case 2
+      operations: [Open]
+
+    # about case 3
+
+    - title: Case 3
+";
+        let spec = TestSpec::parse_as(s, SpecFormat::Yaml, None).unwrap();
+        let formatted = spec
+            .to_string_keeping_comments(SpecFormat::Yaml, s)
+            .unwrap();
+        assert_eq!(
+            "title: Spec title
+depth: 1
+
+cases:
+- title: Case 1
+  remarks:
+  - |
+    One
+
+    Two
+
+- title: Case 2
+  operations:
+  - Open
+
+# about case 3
+
+- title: Case 3
+",
+            formatted
+        );
+        assert_eq!(
+            formatted,
+            spec.to_string_keeping_comments(SpecFormat::Yaml, &formatted)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn toml_comments() {
+        let s = "# header
+title = \"Spec # title\"
+s = '''
+# not a comment
+'''
+
+[[cases]]
+title = \"Case\" # a comment
+";
+        assert_eq!(vec![1, 8], toml_comment_lines(s));
+
+        let spec = TestSpec::parse_as(
+            s.replace("s = '''\n# not a comment\n'''\n", "").as_str(),
+            SpecFormat::Toml,
+            None,
+        )
+        .unwrap();
+        let e = spec
+            .to_string_keeping_comments(SpecFormat::Toml, s)
+            .unwrap_err();
+        assert_eq!(
+            "cannot keep the comment at line 8; TOML specs keep only the comments at the top",
+            e.to_string()
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt;
+
+use super::nodes::nodes;
+use super::SpecFormat;
+use regex::Regex;
+
+/// A spec that could not be read, shown like a compiler error: where the problem is, the
+/// line it is on, the path of the value in the tree and, for a misspelled key, the closest
+/// valid one.
+#[derive(Debug)]
+pub struct SpecError {
+    message: String,
+    file: Option<String>,
+    /// 1-based line and column.
+    position: Option<(usize, usize)>,
+    excerpt: Option<String>,
+    path: Option<String>,
+    help: Option<String>,
+}
+
+impl SpecError {
+    pub(super) fn new(
+        message: String,
+        file: Option<&str>,
+        s: &str,
+        format: SpecFormat,
+        position: Option<(usize, usize)>,
+    ) -> SpecError {
+        let position = position.map(|position| clamp(s, position));
+        let path = match (format, position) {
+            (SpecFormat::Toml, _) | (_, None) => None,
+            (_, Some(position)) => path_at(s, position),
+        };
+        let excerpt = position
+            .and_then(|(line, _)| s.lines().nth(line.checked_sub(1)?))
+            .map(|l| l.trim_end().to_string());
+        SpecError {
+            help: suggestion(&message),
+            message,
+            file: file.map(str::to_string),
+            position,
+            excerpt,
+            path: path.filter(|p| !p.is_empty()),
+        }
+    }
+
+    /// Converts a byte offset into `s` to a 1-based line and column.
+    pub(super) fn position_of(s: &str, offset: usize) -> (usize, usize) {
+        let before = &s[..offset.min(s.len())];
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rsplit('\n')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .count()
+            + 1;
+        (line, column)
+    }
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = self.file.as_deref().unwrap_or("<input>");
+        f.write_str(&self.message)?;
+        let (line, column) = match self.position {
+            Some(position) => position,
+            None => return write!(f, "\n --> {file}"),
+        };
+
+        let gutter = " ".repeat(line.to_string().len());
+        write!(f, "\n{gutter}--> {file}:{line}:{column}")?;
+        if let Some(excerpt) = &self.excerpt {
+            let indent: usize = excerpt.chars().take(column - 1).map(width).sum();
+            write!(f, "\n{gutter} |")?;
+            write!(f, "\n{line} | {excerpt}")?;
+            write!(f, "\n{gutter} | {}^", " ".repeat(indent))?;
+            if self.path.is_some() || self.help.is_some() {
+                write!(f, "\n{gutter} |")?;
+            }
+        }
+        if let Some(path) = &self.path {
+            write!(f, "\n{gutter} = note: at `{path}`")?;
+        }
+        if let Some(help) = &self.help {
+            write!(f, "\n{gutter} = help: {help}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+/// Columns taken by `c` in a terminal; East Asian characters are twice as wide.
+fn width(c: char) -> usize {
+    match c {
+        '\u{1100}'..='\u{115f}' | '\u{2e80}'..='\u{a4cf}' | '\u{ac00}'..='\u{d7a3}' => 2,
+        '\u{f900}'..='\u{faff}' | '\u{fe30}'..='\u{fe4f}' | '\u{ff00}'..='\u{ff60}' => 2,
+        _ => 1,
+    }
+}
+
+/// The valid name closest to the one an "unknown field" or "unknown variant" error is about.
+fn suggestion(message: &str) -> Option<String> {
+    let re = match Regex::new(r"unknown (?:field|variant) `([^`]*)`, expected (.*)") {
+        Ok(re) => re,
+        Err(_) => unreachable!(),
+    };
+    let captures = re.captures(message)?;
+    let unknown = &captures[1];
+    let (best, score) = captures[2]
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|name| (name, strsim::jaro_winkler(unknown, name)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    if score < 0.8 {
+        return None;
+    }
+    Some(format!("did you mean `{best}`?"))
+}
+
+/// Moves a position past the last line of `s`, where errors about an unexpected end of input
+/// point, to the end of that line, and makes the column at least 1 since serde_json counts
+/// from 0 at the start of a line.
+fn clamp(s: &str, (line, column): (usize, usize)) -> (usize, usize) {
+    let lines: Vec<&str> = s.lines().collect();
+    match lines.iter().rposition(|l| !l.trim().is_empty()) {
+        Some(last) if line > last + 1 => (last + 1, lines[last].trim_end().chars().count() + 1),
+        _ => (line, column.max(1)),
+    }
+}
+
+/// Returns the path, such as `cases[3].children[1].operations`, of the YAML or JSON value
+/// at `position`.
+fn path_at(s: &str, position: (usize, usize)) -> Option<String> {
+    nodes(s)
+        .into_iter()
+        .rev()
+        .find(|n| n.position <= position)
+        .map(|n| n.path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testspec::TestSpec;
+
+    #[test]
+    fn unknown_field() {
+        let s = "title: Spec title
+cases:
+  - title: Primary 1
+    children:
+      - title: Secondary 1-1
+        children:
+          - title: Tertiary 1-1-1
+            confirmation:
+              - Shown
+";
+        let e = match TestSpec::parse_as(s, SpecFormat::Yaml, Some("spec.yml")) {
+            Ok(_) => panic!("unknown field accepted"),
+            Err(e) => e.downcast::<SpecError>().unwrap(),
+        };
+        assert_eq!(Some((8, 13)), e.position);
+        assert_eq!(
+            Some("cases[0].children[0].children[0].confirmation"),
+            e.path.as_deref()
+        );
+        assert_eq!(Some("did you mean `confirmations`?"), e.help.as_deref());
+        let rendered = e.to_string();
+        assert!(rendered.contains(
+            "\n --> spec.yml:8:13\n  |\n8 |             confirmation:\n  |             ^\n  |\n"
+        ));
+    }
+
+    #[test]
+    fn invalid_type() {
+        let s = r#"{
+  "title": "Spec title",
+  "cases": [{ "title": "Primary 1", "operations": "Open" }]
+}"#;
+        let e = match TestSpec::parse_as(s, SpecFormat::Json, None) {
+            Ok(_) => panic!("string accepted as a list"),
+            Err(e) => e.downcast::<SpecError>().unwrap(),
+        };
+        assert_eq!(Some("cases[0].operations"), e.path.as_deref());
+        assert_eq!(None, e.help);
+
+        let e = match TestSpec::parse_as("title = 1\n", SpecFormat::Toml, None) {
+            Ok(_) => panic!("integer accepted as the title"),
+            Err(e) => e.downcast::<SpecError>().unwrap(),
+        };
+        assert_eq!(Some((1, 9)), e.position);
+    }
+
+    #[test]
+    fn truncated_json() {
+        let s = "{\n  \"title\": \"Spec title\",\n  \"cases\": [\n";
+        let e = match TestSpec::parse_as(s, SpecFormat::Json, None) {
+            Ok(_) => panic!("truncated JSON accepted"),
+            Err(e) => e.downcast::<SpecError>().unwrap(),
+        };
+        assert_eq!(Some((3, 13)), e.position);
+        assert!(e
+            .to_string()
+            .contains("\n --> <input>:3:13\n  |\n3 |   \"cases\": [\n  |             ^\n"));
+
+        let e = match TestSpec::parse_as("", SpecFormat::Json, None) {
+            Ok(_) => panic!("empty JSON accepted"),
+            Err(e) => e.downcast::<SpecError>().unwrap(),
+        };
+        assert_eq!(Some((1, 1)), e.position);
+    }
+}
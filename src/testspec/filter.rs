@@ -0,0 +1,389 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use anyhow::{bail, ensure, Error};
+
+use super::{Item, TestSpec};
+
+/// A filter expression such as `tag:smoke and priority<=2`.
+///
+/// Predicates are `tag`, `priority`, `id` and `meta.<key>` compared with `:` (or `=`), `!=`,
+/// `<`, `<=`, `>` or `>=`, combined with `and`, `or`, `not` and parentheses. Tags, priority and
+/// metadata are inherited from the enclosing items, and `id` matches the case or any ancestor.
+#[derive(Debug)]
+pub struct Filter(Expr);
+
+#[derive(Debug)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Priority(Op, u32),
+    Predicate(Field, Op, String),
+}
+
+#[derive(Debug)]
+enum Field {
+    Tag,
+    Id,
+    Meta(String),
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Op {
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Eq => ":",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+        }
+    }
+
+    fn test(self, ordering: Ordering) -> bool {
+        match self {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// The attributes of a test case together with those inherited from its ancestors.
+#[derive(Debug, Default, Clone)]
+struct Context {
+    ids: Vec<String>,
+    tags: Vec<String>,
+    priority: Option<u32>,
+    meta: BTreeMap<String, String>,
+}
+
+impl Context {
+    fn child(&self, item: &Item) -> Context {
+        let mut context = self.clone();
+        context.ids.extend(item.id.clone());
+        context.tags.extend(item.tags.iter().cloned());
+        context.priority = item.priority.or(self.priority);
+        context.meta.extend(item.meta.clone());
+        context
+    }
+}
+
+impl Filter {
+    fn matches(&self, context: &Context) -> bool {
+        self.0.matches(context)
+    }
+}
+
+impl Expr {
+    fn matches(&self, context: &Context) -> bool {
+        match self {
+            Expr::And(l, r) => l.matches(context) && r.matches(context),
+            Expr::Or(l, r) => l.matches(context) || r.matches(context),
+            Expr::Not(e) => !e.matches(context),
+            Expr::Priority(op, value) => {
+                matches!(context.priority, Some(p) if op.test(p.cmp(value)))
+            }
+            Expr::Predicate(field, op, value) => {
+                let values: Vec<&String> = match field {
+                    Field::Tag => context.tags.iter().collect(),
+                    Field::Id => context.ids.iter().collect(),
+                    Field::Meta(key) => context.meta.get(key).into_iter().collect(),
+                };
+                match op {
+                    Op::Ne => values.iter().all(|v| compare(v, value) != Ordering::Equal),
+                    op => values.iter().any(|v| op.test(compare(v, value))),
+                }
+            }
+        }
+    }
+}
+
+/// Compares numerically when both sides are numbers, and as strings otherwise.
+fn compare(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Filter, Self::Err> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            bail!("invalid filter: unexpected `{}` in {s}", token.text());
+        }
+        Ok(Filter(expr))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Op(Op),
+    Word(String),
+    Quoted(String),
+}
+
+impl Token {
+    fn text(&self) -> String {
+        match self {
+            Token::Open => "(".to_string(),
+            Token::Close => ")".to_string(),
+            Token::Op(op) => op.symbol().to_string(),
+            Token::Word(w) => w.clone(),
+            Token::Quoted(q) => format!("\"{q}\""),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ':' => Token::Op(Op::Eq),
+            '=' => {
+                chars.next_if_eq(&'=');
+                Token::Op(Op::Eq)
+            }
+            '!' if chars.next_if_eq(&'=').is_some() => Token::Op(Op::Ne),
+            '<' if chars.next_if_eq(&'=').is_some() => Token::Op(Op::Le),
+            '<' => Token::Op(Op::Lt),
+            '>' if chars.next_if_eq(&'=').is_some() => Token::Op(Op::Ge),
+            '>' => Token::Op(Op::Gt),
+            '"' => {
+                let mut quoted = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => quoted.push(c),
+                        None => bail!("invalid filter: unterminated quote in {s}"),
+                    }
+                }
+                Token::Quoted(quoted)
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) =
+                    chars.next_if(|c| !c.is_whitespace() && !"():=!<>\"".contains(*c))
+                {
+                    word.push(c);
+                }
+                Token::Word(word)
+            }
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        match self.tokens.get(self.pos) {
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn or(&mut self) -> anyhow::Result<Expr> {
+        let mut expr = self.and()?;
+        while self.eat_keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> anyhow::Result<Expr> {
+        let mut expr = self.not()?;
+        while self.eat_keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> anyhow::Result<Expr> {
+        if self.eat_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> anyhow::Result<Expr> {
+        let field = match self.next() {
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                ensure!(
+                    self.next() == Some(Token::Close),
+                    "invalid filter: missing `)`"
+                );
+                return Ok(expr);
+            }
+            Some(Token::Word(w)) => w,
+            Some(t) => bail!("invalid filter: expected a field, found `{}`", t.text()),
+            None => bail!("invalid filter: unexpected end of expression"),
+        };
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            _ => bail!("invalid filter: expected an operator after `{field}`"),
+        };
+        let value = match self.next() {
+            Some(Token::Word(v)) | Some(Token::Quoted(v)) => v,
+            _ => bail!("invalid filter: expected a value after `{field}`"),
+        };
+        let field = match &field[..] {
+            "priority" => match value.parse() {
+                Ok(value) => return Ok(Expr::Priority(op, value)),
+                Err(_) => bail!("invalid filter: priority must be a number, found `{value}`"),
+            },
+            "tag" | "tags" => Field::Tag,
+            "id" => Field::Id,
+            f => match f.strip_prefix("meta.") {
+                Some(key) if !key.is_empty() => Field::Meta(key.to_string()),
+                _ => bail!("invalid filter: unknown field `{f}`"),
+            },
+        };
+        Ok(Expr::Predicate(field, op, value))
+    }
+}
+
+impl TestSpec {
+    /// Removes the test cases that do not match `filter`, along with every item left without
+    /// test cases.
+    pub fn filter(&mut self, filter: &Filter) {
+        retain(&mut self.cases, 1, self.depth, &Context::default(), filter);
+    }
+}
+
+fn retain(items: &mut Vec<Item>, level: usize, depth: usize, parent: &Context, filter: &Filter) {
+    items.retain_mut(|item| {
+        let context = parent.child(item);
+        if level == depth {
+            return filter.matches(&context);
+        }
+        retain(&mut item.children, level + 1, depth, &context, filter);
+        !item.children.is_empty()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = "
+title: Spec title
+depth: 2
+
+cases:
+  - title: Login
+    tags: [auth]
+    priority: 2
+    children:
+      - title: Valid password
+        tags: [smoke]
+        priority: 1
+      - title: Invalid password
+      - id: LOGIN-3
+        title: Locked account
+        meta:
+          owner: alice
+  - title: Payment
+    children:
+      - title: Card
+        tags: [smoke]
+  - title: Admin
+";
+
+    fn titles(filter: &str) -> Vec<String> {
+        let mut spec: TestSpec = SPEC.parse().unwrap();
+        spec.filter(&filter.parse().unwrap());
+        spec.cases
+            .iter()
+            .flat_map(|p| {
+                p.children
+                    .iter()
+                    .map(move |c| format!("{}/{}", p.title, c.title))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tag_and_priority() {
+        assert_eq!(
+            vec!["Login/Valid password", "Payment/Card"],
+            titles("tag:smoke")
+        );
+        assert_eq!(
+            vec!["Login/Valid password"],
+            titles("tag:smoke and priority<=2")
+        );
+        assert_eq!(
+            vec![
+                "Login/Valid password",
+                "Login/Invalid password",
+                "Login/Locked account"
+            ],
+            titles("tag:auth")
+        );
+        assert_eq!(
+            vec!["Login/Invalid password", "Login/Locked account"],
+            titles("priority = 2")
+        );
+    }
+
+    #[test]
+    fn boolean_operators() {
+        assert_eq!(
+            vec!["Login/Locked account", "Payment/Card"],
+            titles("(tag:auth and not priority<2 and meta.owner:alice) or id:NONE or tag:smoke and not tag:auth")
+        );
+        assert_eq!(vec!["Login/Locked account"], titles("id:LOGIN-3"));
+        assert_eq!(vec!["Payment/Card"], titles("tag!=auth"));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!("tag".parse::<Filter>().is_err());
+        assert!("tag:".parse::<Filter>().is_err());
+        assert!("priority<=high".parse::<Filter>().is_err());
+        assert!("color:red".parse::<Filter>().is_err());
+        assert!("(tag:smoke".parse::<Filter>().is_err());
+        assert!("tag:smoke tag:auth".parse::<Filter>().is_err());
+    }
+}
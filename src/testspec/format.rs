@@ -0,0 +1,166 @@
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{bail, Error};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::error::SpecError;
+use super::TestSpec;
+
+/// Serialization format of a spec file.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SpecFormat {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl FromStr for SpecFormat {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<SpecFormat, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "yaml" | "yml" => Ok(SpecFormat::Yaml),
+            "json" => Ok(SpecFormat::Json),
+            "toml" => Ok(SpecFormat::Toml),
+            _ => bail!("invalid input format: {s}"),
+        }
+    }
+}
+
+impl SpecFormat {
+    /// Picks the format from the file extension, or `None` for an unknown extension.
+    pub fn from_path(path: &str) -> Option<SpecFormat> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        extension.parse().ok()
+    }
+
+    /// Guesses the format of `s`: JSON starts with `{`, TOML with a `[table]` or `key = value`
+    /// line, and anything else is YAML.
+    pub fn detect(s: &str) -> SpecFormat {
+        let first = s
+            .lines()
+            .map(str::trim)
+            .find(|l| !l.is_empty() && !l.starts_with('#'));
+        match first {
+            Some(l) if l.starts_with('{') => SpecFormat::Json,
+            Some(l) if l.starts_with('[') => SpecFormat::Toml,
+            Some(l) if matches!(l.split_once('='), Some((key, _)) if !key.contains(':')) => {
+                SpecFormat::Toml
+            }
+            _ => SpecFormat::Yaml,
+        }
+    }
+}
+
+impl SpecFormat {
+    /// Deserializes `s`, read from `file`, failing with a [`SpecError`] that points at the
+    /// problem.
+    pub(super) fn deserialize<T: DeserializeOwned>(
+        self,
+        s: &str,
+        file: Option<&str>,
+    ) -> anyhow::Result<T> {
+        let result = match self {
+            SpecFormat::Yaml => serde_yaml::from_str(s).map_err(|e| {
+                let position = e.location().map(|l| (l.line(), l.column()));
+                (without_location(e.to_string(), position), position)
+            }),
+            SpecFormat::Json => serde_json::from_str(s).map_err(|e| {
+                let position = Some((e.line(), e.column())).filter(|(line, _)| *line > 0);
+                (without_location(e.to_string(), position), position)
+            }),
+            SpecFormat::Toml => toml::from_str(s).map_err(|e| {
+                let position = e.span().map(|span| SpecError::position_of(s, span.start));
+                (e.message().to_string(), position)
+            }),
+        };
+        result
+            .map_err(|(message, position)| SpecError::new(message, file, s, self, position).into())
+    }
+
+    pub(super) fn serialize<T: Serialize>(self, value: &T) -> anyhow::Result<String> {
+        let s = match self {
+            SpecFormat::Yaml => serde_yaml::to_string(value)?,
+            SpecFormat::Json => serde_json::to_string_pretty(value)? + "\n",
+            SpecFormat::Toml => toml::to_string(value)?,
+        };
+        Ok(s)
+    }
+}
+
+/// Strips the path prefix and the ` at line 1 column 2` suffix that serde_yaml and serde_json
+/// add, since [`SpecError`] shows both on lines of their own.
+fn without_location(message: String, position: Option<(usize, usize)>) -> String {
+    let message = match position {
+        Some((line, column)) => message
+            .strip_suffix(&format!(" at line {line} column {column}"))
+            .map(str::to_string)
+            .unwrap_or(message),
+        None => message,
+    };
+    match message.split_once(": ") {
+        Some((path, rest)) if !path.contains(' ') => rest.to_string(),
+        _ => message,
+    }
+}
+
+impl TestSpec {
+    /// Parses a spec without following its includes; `file` names it in errors.
+    pub fn parse_as(s: &str, format: SpecFormat, file: Option<&str>) -> anyhow::Result<TestSpec> {
+        let spec: TestSpec = format.deserialize(s, file)?;
+        spec.validate_items(false)?;
+        Ok(spec)
+    }
+
+    pub fn to_string_as(&self, format: SpecFormat) -> anyhow::Result<String> {
+        format.serialize(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YAML: &str = "
+title: Spec title
+depth: 1
+
+cases:
+  - title: Case 1
+    meta:
+      owner: alice
+    operations:
+      - Open the login page
+";
+
+    #[test]
+    fn detect() {
+        assert_eq!(SpecFormat::Yaml, SpecFormat::detect(YAML));
+        assert_eq!(
+            SpecFormat::Yaml,
+            SpecFormat::detect("# comment\ntitle: a = b")
+        );
+        assert_eq!(SpecFormat::Json, SpecFormat::detect("\n{\"title\": \"a\"}"));
+        assert_eq!(
+            SpecFormat::Toml,
+            SpecFormat::detect("# comment\ntitle = \"a\"")
+        );
+        assert_eq!(
+            SpecFormat::Toml,
+            SpecFormat::detect("[[cases]]\ntitle = \"a\"")
+        );
+        assert_eq!(Some(SpecFormat::Json), SpecFormat::from_path("spec.JSON"));
+        assert_eq!(None, SpecFormat::from_path("spec"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let spec: TestSpec = YAML.parse().unwrap();
+        for format in [SpecFormat::Yaml, SpecFormat::Json, SpecFormat::Toml] {
+            let s = spec.to_string_as(format).unwrap();
+            assert_eq!(format, SpecFormat::detect(&s));
+            assert_eq!(spec, TestSpec::parse_as(&s, format, None).unwrap());
+        }
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::str::FromStr;
+
+use anyhow::{bail, ensure, Context, Error};
+
+use super::comments::keep_comments;
+use super::include::parse_fragment;
+use super::{Item, SpecFormat, TestSpec};
+
+/// How `TestSpec::assign_ids` numbers items that do not have an ID yet.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum IdScheme {
+    /// Dotted IDs derived from the parent, e.g. `1`, `1.2`, `1.2.3`.
+    Hierarchical,
+    /// Zero-padded sequential IDs for test cases, e.g. `LOGIN-001`; categories are left
+    /// unnumbered.
+    Sequential { prefix: String, width: usize },
+}
+
+impl FromStr for IdScheme {
+    type Err = Error;
+    /// Parses `hierarchical`, or a pattern whose trailing `#`s mark the digits (`LOGIN-###`).
+    fn from_str(s: &str) -> Result<IdScheme, Self::Err> {
+        if s.to_lowercase() == "hierarchical" {
+            return Ok(IdScheme::Hierarchical);
+        }
+        let prefix = s.trim_end_matches('#');
+        let width = s.len() - prefix.len();
+        if width == 0 || prefix.contains('#') {
+            bail!("invalid id scheme: {s}");
+        }
+        Ok(IdScheme::Sequential {
+            prefix: prefix.to_string(),
+            width,
+        })
+    }
+}
+
+impl TestSpec {
+    /// Assigns an ID to every item that lacks one. Existing IDs are never changed, and new
+    /// numbers always continue after the highest number already in use, so IDs stay stable
+    /// when cases are inserted or removed.
+    pub fn assign_ids(&mut self, scheme: &IdScheme) {
+        match scheme {
+            IdScheme::Hierarchical => assign_hierarchical_ids(&mut self.cases, ""),
+            IdScheme::Sequential { prefix, width } => self.assign_sequential_ids(prefix, *width),
+        }
+    }
+
+    /// Assigns IDs to the spec `s` read from `path` and to every file it includes, numbered
+    /// as one tree. Returns the spec with its new IDs, then each included file whose IDs
+    /// changed with its new text.
+    ///
+    /// In YAML, each new ID is added as an `id:` line at the start of its item and the rest of
+    /// the text is left as written; other files are written out again in full.
+    pub fn assign_ids_in_files(
+        s: &str,
+        format: SpecFormat,
+        path: Option<&str>,
+        scheme: &IdScheme,
+    ) -> anyhow::Result<(String, Vec<(String, String)>)> {
+        let mut expanded = TestSpec::load(s, format, path)?;
+        let missing: Vec<bool> = expanded.items().iter().map(|i| i.id.is_none()).collect();
+        expanded.assign_ids(scheme);
+
+        // The IDs of the items of each file in document order, and the line of each new one.
+        let mut files = Vec::new();
+        let mut ids: BTreeMap<&str, Vec<Option<String>>> = BTreeMap::new();
+        let mut new_ids: BTreeMap<&str, Vec<(Option<usize>, &str)>> = BTreeMap::new();
+        for (item, missing) in expanded.items().into_iter().zip(missing) {
+            if let Some(source) = &item.source {
+                if !ids.contains_key(&source.file[..]) {
+                    files.push(&source.file[..]);
+                }
+                ids.entry(&source.file).or_default().push(item.id.clone());
+                if let (true, Some(id)) = (missing, &item.id) {
+                    new_ids
+                        .entry(&source.file)
+                        .or_default()
+                        .push((source.line, id));
+                }
+            }
+        }
+
+        let main = path.unwrap_or("<stdin>");
+        let mut spec: TestSpec = format.deserialize(s, Some(main))?;
+        set_ids(&mut spec.cases, main, ids.remove(main).unwrap_or_default())?;
+        let parsed = |s: &str| format.deserialize::<TestSpec>(s, Some(main)).ok();
+        let spec = match insert_ids(s, format, &new_ids.remove(main).unwrap_or_default()) {
+            Some(inserted) if parsed(&inserted).as_ref() == Some(&spec) => inserted,
+            _ => spec.to_string_keeping_comments(format, s)?,
+        };
+
+        let mut fragments = Vec::new();
+        for file in files.into_iter().filter(|f| *f != main) {
+            let original =
+                read_to_string(file).with_context(|| format!("failed to read {file}"))?;
+            let format = SpecFormat::from_path(file).unwrap_or(SpecFormat::Yaml);
+            let mut fragment = parse_fragment(&original, format, file)?;
+            set_ids(
+                &mut fragment.items,
+                file,
+                ids.remove(file).unwrap_or_default(),
+            )?;
+            let parsed = |s: &str| parse_fragment(s, format, file).ok().map(|f| f.items);
+            let s = match insert_ids(&original, format, &new_ids.remove(file).unwrap_or_default()) {
+                Some(inserted) if parsed(&inserted).as_ref() == Some(&fragment.items) => inserted,
+                _ => {
+                    let s = match &fragment.items[..] {
+                        [item] if !fragment.list => format.serialize(item)?,
+                        items => format.serialize(&items)?,
+                    };
+                    keep_comments(format, &original, &s)?
+                }
+            };
+            if s != original {
+                fragments.push((file.to_string(), s));
+            }
+        }
+        Ok((spec, fragments))
+    }
+
+    /// Numbers the test cases in document order, after the highest number in use anywhere
+    /// in the tree.
+    fn assign_sequential_ids(&mut self, prefix: &str, width: usize) {
+        let mut next = next_number(prefix, self.items().into_iter().map(|i| &i.id));
+        for case in self.test_cases_mut() {
+            case.id.get_or_insert_with(|| {
+                let id = format!("{prefix}{next:0width$}");
+                next += 1;
+                id
+            });
+        }
+    }
+}
+
+fn assign_hierarchical_ids(items: &mut [Item], prefix: &str) {
+    let mut next = next_number(prefix, items.iter().map(|i| &i.id));
+    for item in items.iter_mut() {
+        let id = item.id.get_or_insert_with(|| take(&mut next, prefix));
+        let prefix = format!("{id}.");
+        assign_hierarchical_ids(&mut item.children, &prefix);
+    }
+}
+
+/// Adds an `id:` line to the YAML text `s` for each of `ids`, on the line where its item
+/// starts, leaving every other line as written. Returns `None` when the text cannot be edited
+/// in place: it is not YAML, a line is unknown, or an item is not a block mapping.
+fn insert_ids(s: &str, format: SpecFormat, ids: &[(Option<usize>, &str)]) -> Option<String> {
+    if ids.is_empty() {
+        return Some(s.to_string());
+    }
+    if format != SpecFormat::Yaml {
+        return None;
+    }
+    let mut by_line = BTreeMap::new();
+    for (line, id) in ids {
+        if by_line.insert((*line)?, *id).is_some() {
+            return None;
+        }
+    }
+
+    let mut buf = String::new();
+    for (i, l) in s.split_inclusive('\n').enumerate() {
+        let id = match by_line.get(&(i + 1)) {
+            Some(id) => id,
+            None => {
+                buf.push_str(l);
+                continue;
+            }
+        };
+        // The first key starts after the indentation and the `- ` of a sequence entry.
+        let indent = l.len() - l.trim_start().len();
+        let start = match l[indent..].strip_prefix('-') {
+            Some(rest) => indent + 1 + rest.len() - rest.trim_start_matches(' ').len(),
+            None => indent,
+        };
+        let key = &l[start..];
+        if key.trim().is_empty() || key.starts_with(['{', '[', '#', '-']) {
+            return None;
+        }
+        let value = serde_yaml::to_string(id).ok()?;
+        buf.push_str(&l[..start]);
+        buf.push_str(&format!("id: {}\n", value.trim_end()));
+        buf.push_str(&" ".repeat(start));
+        buf.push_str(key);
+    }
+    Some(buf)
+}
+
+/// Sets the IDs of the items read from `file`, in document order; include entries are skipped.
+fn set_ids(items: &mut [Item], file: &str, ids: Vec<Option<String>>) -> anyhow::Result<()> {
+    ensure!(
+        count_own_items(items) == ids.len(),
+        "{file} is included more than once, so its items cannot get unique IDs"
+    );
+    set_ids_rec(items, &mut ids.into_iter());
+    Ok(())
+}
+
+fn count_own_items(items: &[Item]) -> usize {
+    items
+        .iter()
+        .filter(|i| i.include.is_none())
+        .map(|i| 1 + count_own_items(&i.children))
+        .sum()
+}
+
+fn set_ids_rec(items: &mut [Item], ids: &mut impl Iterator<Item = Option<String>>) {
+    for item in items.iter_mut().filter(|i| i.include.is_none()) {
+        item.id = ids.next().flatten();
+        set_ids_rec(&mut item.children, ids);
+    }
+}
+
+/// Returns the number following the highest `{prefix}{number}` ID among `ids`.
+fn next_number<'a>(prefix: &str, ids: impl Iterator<Item = &'a Option<String>>) -> usize {
+    ids.flatten()
+        .filter_map(|id| id.strip_prefix(prefix)?.parse::<usize>().ok())
+        .max()
+        .map_or(1, |n| n + 1)
+}
+
+fn take(next: &mut usize, prefix: &str) -> String {
+    let id = format!("{prefix}{next}");
+    *next += 1;
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = "
+title: Spec title
+cases:
+  - title: Primary 1
+    children:
+      - title: Secondary 1-1
+        children:
+          - title: Tertiary 1-1-1
+          - id: LOGIN-007
+            title: Tertiary 1-1-2
+          - title: Tertiary 1-1-3
+  - id: '5'
+    title: Primary 2
+    children:
+      - title: Secondary 2-1
+        children:
+          - id: 5.1.4
+            title: Tertiary 2-1-1
+          - title: Tertiary 2-1-2
+";
+
+    fn case_ids(spec: &mut TestSpec) -> Vec<Option<&str>> {
+        spec.test_cases_mut()
+            .into_iter()
+            .map(|c| c.id.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn parse_scheme() {
+        assert_eq!(
+            IdScheme::Hierarchical,
+            "hierarchical".parse::<IdScheme>().unwrap()
+        );
+        assert_eq!(
+            IdScheme::Sequential {
+                prefix: "LOGIN-".to_string(),
+                width: 3
+            },
+            "LOGIN-###".parse::<IdScheme>().unwrap()
+        );
+        assert!("LOGIN-".parse::<IdScheme>().is_err());
+        assert!("A#B#".parse::<IdScheme>().is_err());
+    }
+
+    #[test]
+    fn hierarchical() {
+        let mut spec: TestSpec = SPEC.parse().unwrap();
+        spec.assign_ids(&IdScheme::Hierarchical);
+
+        let primary_ids: Vec<_> = spec.cases.iter().map(|p| p.id.as_deref()).collect();
+        assert_eq!(vec![Some("6"), Some("5")], primary_ids);
+        assert_eq!(Some("5.1"), spec.cases[1].children[0].id.as_deref());
+        assert_eq!(
+            vec![
+                Some("6.1.1"),
+                Some("LOGIN-007"),
+                Some("6.1.2"),
+                Some("5.1.4"),
+                Some("5.1.5"),
+            ],
+            case_ids(&mut spec)
+        );
+    }
+
+    #[test]
+    fn sequential() {
+        let mut spec: TestSpec = SPEC.parse().unwrap();
+        spec.assign_ids(&"LOGIN-###".parse().unwrap());
+
+        let ids: Vec<_> = spec.items().iter().map(|i| i.id.as_deref()).collect();
+        assert_eq!(
+            vec![
+                None,
+                None,
+                Some("LOGIN-008"),
+                Some("LOGIN-007"),
+                Some("LOGIN-009"),
+                Some("5"),
+                None,
+                Some("5.1.4"),
+                Some("LOGIN-010"),
+            ],
+            ids
+        );
+    }
+
+    #[test]
+    fn included_files() {
+        let temp = mktemp::Temp::new_dir().unwrap();
+        let dir = temp.as_path();
+        let main = "title: Spec title
+depth: 2
+
+cases:
+  - title: Primary 1
+    children:
+      - title: Secondary 1-1
+
+      - id: '1.5'
+        title: Secondary 1-2
+
+  - include: login.yml
+";
+        std::fs::write(dir.join("main.yml"), main).unwrap();
+        std::fs::write(
+            dir.join("login.yml"),
+            "# Login
+- title: Login
+  children:
+    - title: Valid password # testspecgen:disable
+",
+        )
+        .unwrap();
+
+        let path = dir.join("main.yml");
+        let (spec, fragments) = TestSpec::assign_ids_in_files(
+            main,
+            SpecFormat::Yaml,
+            path.to_str(),
+            &IdScheme::Hierarchical,
+        )
+        .unwrap();
+        assert_eq!(
+            "title: Spec title
+depth: 2
+
+cases:
+  - id: '1'
+    title: Primary 1
+    children:
+      - id: '1.6'
+        title: Secondary 1-1
+
+      - id: '1.5'
+        title: Secondary 1-2
+
+  - include: login.yml
+",
+            spec
+        );
+        assert_eq!(
+            vec![(
+                dir.join("login.yml").display().to_string(),
+                "# Login
+- id: '2'
+  title: Login
+  children:
+    - id: '2.1'
+      title: Valid password # testspecgen:disable
+"
+                .to_string()
+            )],
+            fragments
+        );
+    }
+
+    #[test]
+    fn flow_mappings_are_written_out() {
+        let s = "title: Spec title\ndepth: 1\ncases: [{ title: Case }]\n";
+        let (spec, _) =
+            TestSpec::assign_ids_in_files(s, SpecFormat::Yaml, None, &IdScheme::Hierarchical)
+                .unwrap();
+        assert_eq!(
+            "title: Spec title\ndepth: 1\ncases:\n- id: '1'\n  title: Case\n",
+            spec
+        );
+    }
+}
@@ -0,0 +1,397 @@
+use std::fmt;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context};
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::Marker;
+
+use super::{Item, SpecFormat, TestSpec};
+
+/// Where an item was read from.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Source {
+    pub file: String,
+    /// 1-based; unknown for TOML.
+    pub line: Option<usize>,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{line}", self.file),
+            None => f.write_str(&self.file),
+        }
+    }
+}
+
+impl TestSpec {
+    /// Parses the spec read from `path` (`None` for stdin) and replaces every `include` entry
+    /// with the items of the files it names, resolved relative to the including file. Each
+    /// item records the file and line it came from.
+    pub fn load(s: &str, format: SpecFormat, path: Option<&str>) -> anyhow::Result<TestSpec> {
+        let file = path.unwrap_or("<stdin>");
+        let mut spec: TestSpec = format.deserialize(s, Some(file))?;
+        set_sources(&mut spec.cases, file, item_lines(s, format, false));
+
+        let mut stack = Vec::new();
+        if let Some(path) = path {
+            stack.push(canonicalize(Path::new(path))?);
+        }
+        let dir = path
+            .and_then(|p| Path::new(p).parent())
+            .unwrap_or_else(|| Path::new(""));
+        expand(&mut spec.cases, dir, &mut stack)?;
+
+        spec.validate()?;
+        Ok(spec)
+    }
+}
+
+fn expand(items: &mut Vec<Item>, dir: &Path, stack: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let mut expanded = Vec::new();
+    for mut item in items.drain(..) {
+        let pattern = match item.include.take() {
+            Some(pattern) => pattern,
+            None => {
+                expand(&mut item.children, dir, stack)?;
+                expanded.push(item);
+                continue;
+            }
+        };
+        ensure!(
+            item == Item {
+                source: item.source.clone(),
+                ..Default::default()
+            },
+            "{}: an include entry cannot have other fields",
+            source_of(&item)
+        );
+        let paths = include_paths(dir, &pattern).with_context(|| source_of(&item))?;
+        for path in paths {
+            expanded.extend(load_fragment(&path, stack)?);
+        }
+    }
+    *items = expanded;
+    Ok(())
+}
+
+fn source_of(item: &Item) -> String {
+    match &item.source {
+        Some(source) => source.to_string(),
+        None => "<unknown>".to_string(),
+    }
+}
+
+fn include_paths(dir: &Path, pattern: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let joined = dir.join(pattern);
+    if !pattern.contains(['*', '?', '[']) {
+        return Ok(vec![joined]);
+    }
+
+    let joined = match joined.to_str() {
+        Some(s) => s.to_string(),
+        None => bail!("invalid include pattern `{pattern}`"),
+    };
+    let mut paths = glob::glob(&joined)
+        .with_context(|| format!("invalid include pattern `{pattern}`"))?
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+    ensure!(!paths.is_empty(), "include `{pattern}` matched no file");
+    Ok(paths)
+}
+
+/// Reads a file holding a single item or a list of items, expanding its own includes.
+fn load_fragment(path: &Path, stack: &mut Vec<PathBuf>) -> anyhow::Result<Vec<Item>> {
+    let file = path.display().to_string();
+    let canonical = canonicalize(path)?;
+    if let Some(start) = stack.iter().position(|p| *p == canonical) {
+        let cycle: Vec<_> = stack[start..]
+            .iter()
+            .chain([&canonical])
+            .map(|p| p.display().to_string())
+            .collect();
+        bail!("include cycle: {}", cycle.join(" -> "));
+    }
+
+    let s = read_to_string(path).with_context(|| format!("failed to read {file}"))?;
+    let format = SpecFormat::from_path(&file).unwrap_or(SpecFormat::Yaml);
+    let mut items = parse_fragment(&s, format, &file)?.items;
+    set_sources(&mut items, &file, item_lines(&s, format, true));
+
+    stack.push(canonical);
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    expand(&mut items, dir, stack)?;
+    stack.pop();
+
+    Ok(items)
+}
+
+/// The items of an included file, without its own includes expanded.
+pub(super) struct Fragment {
+    pub items: Vec<Item>,
+    /// Whether the file holds a list rather than a single item.
+    pub list: bool,
+}
+
+pub(super) fn parse_fragment(s: &str, format: SpecFormat, file: &str) -> anyhow::Result<Fragment> {
+    let first = s
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'));
+    let list = format != SpecFormat::Toml && matches!(first, Some(l) if l.starts_with(['-', '[']));
+    let items = if list {
+        format.deserialize(s, Some(file))?
+    } else {
+        vec![format.deserialize(s, Some(file))?]
+    };
+    Ok(Fragment { items, list })
+}
+
+fn canonicalize(path: &Path) -> anyhow::Result<PathBuf> {
+    path.canonicalize()
+        .with_context(|| format!("failed to read {}", path.display()))
+}
+
+/// Records `file` and the item lines, which are in document order, on `items`. Lines are
+/// left out if they do not line up with the items.
+fn set_sources(items: &mut [Item], file: &str, lines: Vec<usize>) {
+    let mut lines = if lines.len() == count_items(items) {
+        Some(lines.into_iter())
+    } else {
+        None
+    };
+    set_sources_rec(items, file, &mut lines);
+}
+
+fn count_items(items: &[Item]) -> usize {
+    items.iter().map(|i| 1 + count_items(&i.children)).sum()
+}
+
+fn set_sources_rec<I: Iterator<Item = usize>>(
+    items: &mut [Item],
+    file: &str,
+    lines: &mut Option<I>,
+) {
+    for item in items {
+        item.source = Some(Source {
+            file: file.to_string(),
+            line: lines.as_mut().and_then(|l| l.next()),
+        });
+        set_sources_rec(&mut item.children, file, lines);
+    }
+}
+
+/// Returns the line of every item mapping in document order. YAML and JSON are both read by
+/// the YAML parser; TOML has no line information.
+fn item_lines(s: &str, format: SpecFormat, fragment: bool) -> Vec<usize> {
+    if format == SpecFormat::Toml {
+        return Vec::new();
+    }
+    let mut receiver = ItemLines {
+        fragment,
+        stack: Vec::new(),
+        lines: Vec::new(),
+    };
+    match Parser::new_from_str(s).load(&mut receiver, false) {
+        Ok(()) => receiver.lines,
+        Err(_) => Vec::new(),
+    }
+}
+
+enum Frame {
+    Map {
+        item: bool,
+        key: Option<String>,
+        at_key: bool,
+    },
+    Seq {
+        items: bool,
+    },
+}
+
+/// Tracks the mappings that are items: the entries of `cases` and `children`, and the root of
+/// a fragment file.
+struct ItemLines {
+    fragment: bool,
+    stack: Vec<Frame>,
+    lines: Vec<usize>,
+}
+
+impl ItemLines {
+    fn value_done(&mut self) {
+        if let Some(Frame::Map { at_key, .. }) = self.stack.last_mut() {
+            *at_key = true;
+        }
+    }
+}
+
+impl MarkedEventReceiver for ItemLines {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::MappingStart(..) => {
+                let item = match self.stack.last() {
+                    None => self.fragment,
+                    Some(Frame::Seq { items }) => *items,
+                    Some(Frame::Map { .. }) => false,
+                };
+                if item {
+                    self.lines.push(mark.line());
+                }
+                self.stack.push(Frame::Map {
+                    item,
+                    key: None,
+                    at_key: true,
+                });
+            }
+            Event::SequenceStart(..) => {
+                let root = self.stack.len() == 1;
+                let items = match self.stack.last() {
+                    None => self.fragment,
+                    Some(Frame::Map {
+                        item,
+                        key: Some(key),
+                        at_key: false,
+                    }) => (*item && key == "children") || (!*item && root && key == "cases"),
+                    _ => false,
+                };
+                self.stack.push(Frame::Seq { items });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+                self.value_done();
+            }
+            Event::Scalar(value, ..) => match self.stack.last_mut() {
+                Some(Frame::Map { key, at_key, .. }) if *at_key => {
+                    *key = Some(value);
+                    *at_key = false;
+                }
+                _ => self.value_done(),
+            },
+            Event::Alias(_) => self.value_done(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn write(dir: &Path, name: &str, s: &str) {
+        fs::write(dir.join(name), s).unwrap();
+    }
+
+    #[test]
+    fn includes() {
+        let temp = mktemp::Temp::new_dir().unwrap();
+        let dir = temp.as_path();
+        fs::create_dir(dir.join("login")).unwrap();
+        write(
+            dir,
+            "main.yml",
+            "title: Spec title
+depth: 2
+
+cases:
+  - include: login.yml
+  - title: Payment
+    children:
+      - include: payment/*.json
+",
+        );
+        write(
+            dir,
+            "login.yml",
+            "title: Login
+children:
+  - include: login/*.yml
+",
+        );
+        write(
+            dir,
+            "login/valid.yml",
+            "- title: Valid password
+
+- title: Invalid password
+",
+        );
+        fs::create_dir(dir.join("payment")).unwrap();
+        write(dir, "payment/card.json", "{\n  \"title\": \"Card\"\n}\n");
+
+        let path = dir.join("main.yml");
+        let path = path.to_str().unwrap();
+        let spec = TestSpec::load(
+            &fs::read_to_string(path).unwrap(),
+            SpecFormat::Yaml,
+            Some(path),
+        )
+        .unwrap();
+
+        let sources: Vec<_> = spec
+            .items()
+            .iter()
+            .map(|i| {
+                let source = i.source.as_ref().unwrap();
+                let file = source.file.strip_prefix(dir.to_str().unwrap()).unwrap();
+                format!("{} {file}:{}", i.title, source.line.unwrap())
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                "Login /login.yml:1",
+                "Valid password /login/valid.yml:1",
+                "Invalid password /login/valid.yml:3",
+                "Payment /main.yml:6",
+                "Card /payment/card.json:1",
+            ],
+            sources
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        let temp = mktemp::Temp::new_dir().unwrap();
+        let dir = temp.as_path();
+        write(dir, "main.yml", "title: Spec\ncases:\n  - include: a.yml\n");
+        write(dir, "a.yml", "title: A\nchildren:\n  - include: b.yml\n");
+        write(dir, "b.yml", "title: B\nchildren:\n  - include: a.yml\n");
+
+        let path = dir.join("main.yml");
+        let path = path.to_str().unwrap();
+        let err = TestSpec::load(
+            &fs::read_to_string(path).unwrap(),
+            SpecFormat::Yaml,
+            Some(path),
+        )
+        .unwrap_err();
+        assert!(err.to_string().starts_with("include cycle: "));
+        assert!(err.to_string().ends_with("a.yml"));
+    }
+
+    #[test]
+    fn validation_reports_source() {
+        let err = TestSpec::load(
+            "title: Spec\ndepth: 1\ncases:\n  - title: Case\n    children:\n      - title: Child\n",
+            SpecFormat::Yaml,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            "`Case` at `cases[0]` (<stdin>:4) is a test case (level 1) and cannot have children",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn includes_are_checked_once_expanded() {
+        let s = "title: Spec\ndepth: 1\ncases:\n  - include: login.yml\n";
+        let spec = TestSpec::parse_as(s, SpecFormat::Yaml, None).unwrap();
+        assert_eq!(
+            "include `login.yml` at `cases[0]` was not resolved",
+            spec.validate().unwrap_err().to_string()
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+
+/// A mapping, sequence, key or scalar of a YAML or JSON document.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Node {
+    /// 1-based line and column where the node starts.
+    pub position: (usize, usize),
+    /// Path of the value, such as `cases[3].children[1].operations`; a key has the path of
+    /// its value.
+    pub path: String,
+    pub key: bool,
+    /// Style of a scalar.
+    pub style: Option<TScalarStyle>,
+    /// Where the next node or end of a collection starts, for a block scalar.
+    pub end: Option<(usize, usize)>,
+}
+
+/// Returns the nodes of `s` in document order, up to the first syntax error if there is one.
+pub(super) fn nodes(s: &str) -> Vec<Node> {
+    let mut receiver = Nodes {
+        stack: Vec::new(),
+        nodes: Vec::new(),
+    };
+    let _ = Parser::new_from_str(s).load(&mut receiver, false);
+    receiver.nodes
+}
+
+enum Frame {
+    Map { path: String, key: Option<String> },
+    Seq { path: String, index: usize },
+}
+
+struct Nodes {
+    stack: Vec<Frame>,
+    nodes: Vec<Node>,
+}
+
+impl Nodes {
+    fn child_path(&self) -> String {
+        match self.stack.last() {
+            Some(Frame::Map {
+                path,
+                key: Some(key),
+            }) if path.is_empty() => key.clone(),
+            Some(Frame::Map {
+                path,
+                key: Some(key),
+            }) => format!("{path}.{key}"),
+            Some(Frame::Seq { path, index }) => format!("{path}[{index}]"),
+            Some(Frame::Map { path, key: None }) => path.clone(),
+            None => String::new(),
+        }
+    }
+
+    fn value_done(&mut self) {
+        match self.stack.last_mut() {
+            Some(Frame::Map { key, .. }) => *key = None,
+            Some(Frame::Seq { index, .. }) => *index += 1,
+            None => {}
+        }
+    }
+
+    fn push(&mut self, position: (usize, usize), path: String, key: bool) {
+        self.nodes.push(Node {
+            position,
+            path,
+            key,
+            style: None,
+            end: None,
+        });
+    }
+}
+
+impl MarkedEventReceiver for Nodes {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        let position = (mark.line(), mark.col() + 1);
+        if let Some(node) = self.nodes.last_mut() {
+            if matches!(
+                node.style,
+                Some(TScalarStyle::Literal | TScalarStyle::Folded)
+            ) && node.end.is_none()
+            {
+                node.end = Some(position);
+            }
+        }
+        match event {
+            Event::MappingStart(..) => {
+                let path = self.child_path();
+                self.push(position, path.clone(), false);
+                self.stack.push(Frame::Map { path, key: None });
+            }
+            Event::SequenceStart(..) => {
+                let path = self.child_path();
+                self.push(position, path.clone(), false);
+                self.stack.push(Frame::Seq { path, index: 0 });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+                self.value_done();
+            }
+            Event::Scalar(value, style, ..) => {
+                let key = match self.stack.last_mut() {
+                    Some(Frame::Map {
+                        key: key @ None, ..
+                    }) => {
+                        *key = Some(value);
+                        true
+                    }
+                    _ => false,
+                };
+                let path = self.child_path();
+                self.push(position, path, key);
+                if let Some(node) = self.nodes.last_mut() {
+                    node.style = Some(style);
+                }
+                if !key {
+                    self.value_done();
+                }
+            }
+            Event::Alias(..) => self.value_done(),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, ensure, Context};
+
+use super::{Item, TestSpec};
+
+type Binding = BTreeMap<String, String>;
+
+impl TestSpec {
+    /// Replaces every test case with `parameters` or `examples` by one case per combination
+    /// of values, with the `{{name}}` placeholders substituted.
+    ///
+    /// Expanded cases get the ID `{id}[name=value,...]` built from their values, so that
+    /// adding or removing a value leaves the IDs of the other combinations unchanged. Their
+    /// title is suffixed with the values when it has no placeholder, so that every
+    /// combination stays distinguishable.
+    pub fn expand_parameters(&mut self) -> anyhow::Result<()> {
+        expand_items(&mut self.cases, 1, self.depth)
+    }
+}
+
+fn expand_items(items: &mut Vec<Item>, level: usize, depth: usize) -> anyhow::Result<()> {
+    if level < depth {
+        for item in items.iter_mut() {
+            expand_items(&mut item.children, level + 1, depth)?;
+        }
+        return Ok(());
+    }
+
+    let mut expanded = Vec::new();
+    for item in items.drain(..) {
+        if item.parameters.is_empty() && item.examples.is_empty() {
+            expanded.push(item);
+            continue;
+        }
+        let bindings = item
+            .bindings()
+            .with_context(|| format!("`{}`", item.title))?;
+        for binding in &bindings {
+            let case = instantiate(&item, binding).with_context(|| format!("`{}`", item.title))?;
+            expanded.push(case);
+        }
+    }
+    *items = expanded;
+    Ok(())
+}
+
+impl Item {
+    /// Every combination of the parameter values, each joined with every example row.
+    pub fn bindings(&self) -> anyhow::Result<Vec<Binding>> {
+        let mut bindings = vec![Binding::new()];
+        for (name, values) in &self.parameters {
+            ensure!(!values.is_empty(), "parameter `{name}` has no values");
+            bindings = bindings
+                .iter()
+                .flat_map(|b| {
+                    values.iter().map(move |v| {
+                        let mut b = b.clone();
+                        b.insert(name.clone(), v.clone());
+                        b
+                    })
+                })
+                .collect();
+        }
+
+        if self.examples.is_empty() {
+            return Ok(bindings);
+        }
+        Ok(bindings
+            .iter()
+            .flat_map(|b| {
+                self.examples.iter().map(move |row| {
+                    let mut b = b.clone();
+                    b.extend(row.clone());
+                    b
+                })
+            })
+            .collect())
+    }
+}
+
+fn instantiate(item: &Item, binding: &Binding) -> anyhow::Result<Item> {
+    let mut case = item.clone();
+    case.parameters.clear();
+    case.examples.clear();
+
+    let values: Vec<_> = binding.iter().map(|(k, v)| format!("{k}={v}")).collect();
+    case.id = item
+        .id
+        .as_ref()
+        .map(|id| format!("{id}[{}]", values.join(",")));
+    case.title = substitute(&item.title, binding)?;
+    if case.title == item.title {
+        case.title = format!("{} ({})", item.title, values.join(", "));
+    }
+
+    for values in [
+        &mut case.environment,
+        &mut case.preconditions,
+        &mut case.test_data,
+        &mut case.operations,
+        &mut case.confirmations,
+        &mut case.remarks,
+    ] {
+        for value in values.iter_mut() {
+            *value = substitute(value, binding)?;
+        }
+    }
+    for step in case.steps.iter_mut() {
+        step.action = substitute(&step.action, binding)?;
+        for value in [&mut step.expected, &mut step.data].into_iter().flatten() {
+            *value = substitute(value, binding)?;
+        }
+    }
+
+    Ok(case)
+}
+
+/// Replaces `{{name}}` with the value bound to `name`.
+fn substitute(s: &str, binding: &Binding) -> anyhow::Result<String> {
+    let mut result = String::new();
+    let mut rest = s;
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        let name = rest[start + 2..end].trim();
+        let value = match binding.get(name) {
+            Some(value) => value,
+            None => bail!("unknown parameter `{name}`"),
+        };
+        result.push_str(&rest[..start]);
+        result.push_str(value);
+        rest = &rest[end + 2..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matrix_and_examples() {
+        let s = "
+title: Spec title
+depth: 1
+
+cases:
+  - id: LOGIN-1
+    title: Log in as {{role}} on {{browser}}
+    parameters:
+      browser: [chrome, firefox]
+    examples:
+      - role: admin
+      - role: guest
+    operations:
+      - Open {{ browser }}
+  - title: Log out
+    parameters:
+      browser: [chrome]
+";
+        let mut spec: TestSpec = s.parse().unwrap();
+        spec.expand_parameters().unwrap();
+
+        let cases: Vec<_> = spec
+            .cases
+            .iter()
+            .map(|c| (c.id.as_deref(), &c.title[..]))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    Some("LOGIN-1[browser=chrome,role=admin]"),
+                    "Log in as admin on chrome"
+                ),
+                (
+                    Some("LOGIN-1[browser=chrome,role=guest]"),
+                    "Log in as guest on chrome"
+                ),
+                (
+                    Some("LOGIN-1[browser=firefox,role=admin]"),
+                    "Log in as admin on firefox"
+                ),
+                (
+                    Some("LOGIN-1[browser=firefox,role=guest]"),
+                    "Log in as guest on firefox"
+                ),
+                (None, "Log out (browser=chrome)"),
+            ],
+            cases
+        );
+        assert_eq!(vec!["Open firefox"], spec.cases[3].operations);
+        assert!(spec.cases[0].parameters.is_empty());
+    }
+
+    #[test]
+    fn ids_are_stable_when_values_are_added() {
+        let ids = |browsers: &str| {
+            let s = format!(
+                "
+title: Spec title
+depth: 1
+
+cases:
+  - id: LOGIN-1
+    title: Log in on {{{{browser}}}} in {{{{lang}}}}
+    parameters:
+      browser: [{browsers}]
+      lang: [en, ja]
+"
+            );
+            let mut spec: TestSpec = s.parse().unwrap();
+            spec.expand_parameters().unwrap();
+            spec.cases
+                .into_iter()
+                .map(|c| c.id.unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let before = ids("chrome, safari");
+        let after = ids("chrome, firefox, safari");
+        assert_eq!(4, before.len());
+        assert_eq!(6, after.len());
+        assert!(before.iter().all(|id| after.contains(id)));
+        assert!(after.contains(&"LOGIN-1[browser=firefox,lang=ja]".to_string()));
+    }
+
+    #[test]
+    fn unknown_parameter() {
+        let s = "
+title: Spec title
+depth: 1
+
+cases:
+  - title: Log in on {{browser}}
+    parameters:
+      os: [linux]
+";
+        let mut spec: TestSpec = s.parse().unwrap();
+        assert!(spec.expand_parameters().is_err());
+    }
+}
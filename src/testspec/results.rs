@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::bail;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::{Item, SpecFormat, TestSpec};
+
+/// Outcome of a run.
+#[derive(Debug, Clone, Copy, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Fail,
+    Blocked,
+    Skip,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Status::Pass => "Pass",
+            Status::Fail => "Fail",
+            Status::Blocked => "Blocked",
+            Status::Skip => "Skip",
+        };
+        f.write_str(s)
+    }
+}
+
+impl FromStr for Status {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Status, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "pass" | "passed" | "ok" => Ok(Status::Pass),
+            "fail" | "failed" | "ng" => Ok(Status::Fail),
+            "blocked" => Ok(Status::Blocked),
+            "skip" | "skipped" => Ok(Status::Skip),
+            _ => bail!("invalid result status: {s}"),
+        }
+    }
+}
+
+/// The outcome of running one test case.
+#[derive(Debug, Clone, Deserialize, Eq, JsonSchema, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExecutionResult {
+    pub status: Status,
+
+    /// Who ran the test case.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+
+    /// Execution date.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(example = &"2022-08-01")]
+    pub date: Option<String>,
+
+    /// Notes on the run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+
+    /// Link to or key of the defect raised for a failure.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(example = &"BUG-12")]
+    pub defect: Option<String>,
+}
+
+/// Execution results keyed by test case ID, as stored in a results file.
+#[derive(Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Results(pub BTreeMap<String, ExecutionResult>);
+
+impl Results {
+    /// Parses a results file; `file` names it in errors.
+    pub fn parse_as(s: &str, format: SpecFormat, file: Option<&str>) -> anyhow::Result<Results> {
+        format.deserialize(s, file)
+    }
+
+    pub fn to_string_as(&self, format: SpecFormat) -> anyhow::Result<String> {
+        format.serialize(self)
+    }
+}
+
+/// Parses results in the format [`SpecFormat::detect`] guesses.
+impl FromStr for Results {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Results::parse_as(s, SpecFormat::detect(s), None)
+    }
+}
+
+impl TestSpec {
+    /// Attaches each result to the test case with the same ID, replacing any result already
+    /// recorded in the spec.
+    pub fn apply_results(&mut self, results: &Results) -> anyhow::Result<()> {
+        let mut cases: BTreeMap<String, &mut Item> = BTreeMap::new();
+        for case in self.test_cases_mut() {
+            let id = match &case.id {
+                Some(id) => id.clone(),
+                None => continue,
+            };
+            if let Some(first) = cases.get(&id) {
+                bail!(
+                    "test cases {} and {} have the same ID {id}, so results cannot be applied",
+                    describe(first),
+                    describe(case)
+                );
+            }
+            cases.insert(id, case);
+        }
+
+        let unknown: Vec<_> = results
+            .0
+            .keys()
+            .filter(|id| !cases.contains_key(*id))
+            .map(|id| &id[..])
+            .collect();
+        if !unknown.is_empty() {
+            bail!("no test case with ID {}", unknown.join(", "));
+        }
+
+        for (id, result) in results.0.iter() {
+            if let Some(case) = cases.get_mut(id) {
+                case.result = Some(result.clone());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The quoted title of `case`, followed by where it was read from when known.
+fn describe(case: &Item) -> String {
+    match &case.source {
+        Some(source) => format!("`{}` ({source})", case.title),
+        None => format!("`{}`", case.title),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = "
+title: Spec title
+depth: 1
+
+cases:
+  - id: TC-1
+    title: Case 1
+  - id: TC-2
+    title: Case 2
+  - title: Case without ID
+";
+
+    #[test]
+    fn apply() {
+        let results: Results = "
+TC-2:
+  status: fail
+  operator: Alice
+  date: 2022-08-01
+  defect: BUG-12
+"
+        .parse()
+        .unwrap();
+
+        let mut spec: TestSpec = SPEC.parse().unwrap();
+        spec.apply_results(&results).unwrap();
+
+        assert_eq!(None, spec.cases[0].result);
+        assert_eq!(
+            Some(ExecutionResult {
+                status: Status::Fail,
+                operator: Some("Alice".to_string()),
+                date: Some("2022-08-01".to_string()),
+                note: None,
+                defect: Some("BUG-12".to_string()),
+            }),
+            spec.cases[1].result
+        );
+    }
+
+    #[test]
+    fn formats() {
+        let yaml: Results = "TC-1:\n  status: pass\n".parse().unwrap();
+        let json: Results = r#"{ "TC-1": { "status": "pass" } }"#.parse().unwrap();
+        let toml: Results = "[TC-1]\nstatus = \"pass\"\n".parse().unwrap();
+        assert_eq!(yaml, json);
+        assert_eq!(yaml, toml);
+        for format in [SpecFormat::Yaml, SpecFormat::Json, SpecFormat::Toml] {
+            let s = yaml.to_string_as(format).unwrap();
+            assert_eq!(yaml, Results::parse_as(&s, format, None).unwrap());
+        }
+    }
+
+    #[test]
+    fn unknown_id() {
+        let results: Results = "
+TC-3:
+  status: pass
+"
+        .parse()
+        .unwrap();
+
+        let mut spec: TestSpec = SPEC.parse().unwrap();
+        assert!(spec.apply_results(&results).is_err());
+    }
+
+    #[test]
+    fn duplicate_id() {
+        let temp = mktemp::Temp::new_dir().unwrap();
+        let dir = temp.as_path();
+        let main = "title: Spec\ndepth: 1\ncases:\n  - id: TC-1\n    title: Case 1\n  - include: more.yml\n";
+        std::fs::write(dir.join("main.yml"), main).unwrap();
+        std::fs::write(dir.join("more.yml"), "id: TC-1\ntitle: Case 2\n").unwrap();
+
+        let path = dir.join("main.yml");
+        let mut spec = TestSpec::load(main, SpecFormat::Yaml, path.to_str()).unwrap();
+        let results: Results = "TC-1:\n  status: pass\n".parse().unwrap();
+        assert_eq!(
+            format!(
+                "test cases `Case 1` ({}:4) and `Case 2` ({}:1) have the same ID TC-1, so results cannot be applied",
+                path.display(),
+                dir.join("more.yml").display()
+            ),
+            spec.apply_results(&results).unwrap_err().to_string()
+        );
+    }
+}
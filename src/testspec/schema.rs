@@ -0,0 +1,106 @@
+use schemars::generate::SchemaSettings;
+use schemars::Schema;
+
+use super::TestSpec;
+
+impl TestSpec {
+    /// The JSON Schema of spec files, as checked in at `docs/schema.json`. It is derived from
+    /// the spec types, so their doc comments become its descriptions.
+    pub fn schema() -> Schema {
+        SchemaSettings::draft2020_12()
+            .for_deserialize()
+            .into_generator()
+            .into_root_schema_for::<TestSpec>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn errors(spec: Value) -> Vec<String> {
+        let schema = serde_json::to_value(TestSpec::schema()).unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        validator
+            .iter_errors(&spec)
+            .map(|e| format!("{}: {e}", e.instance_path))
+            .collect()
+    }
+
+    #[test]
+    fn checked_in() {
+        let generated = serde_json::to_string_pretty(&TestSpec::schema()).unwrap() + "\n";
+        assert!(
+            generated == include_str!("../../docs/schema.json"),
+            "docs/schema.json is out of date; run `testspecgen schema docs/schema.json`"
+        );
+    }
+
+    #[test]
+    fn starter_spec_is_valid() {
+        let spec: Value =
+            serde_yaml::from_str(include_str!("../../templates/testspec.yml")).unwrap();
+        assert_eq!(Vec::<String>::new(), errors(spec));
+    }
+
+    #[test]
+    fn valid() {
+        let spec = json!({
+            "title": "Login",
+            "depth": 2,
+            "config": {
+                "bom": true,
+                "body_bg_color": "0xffffff",
+                "profiles": { "print": { "font_family": "Arial" } },
+                "lint": { "rules": { "operation-verb": "off" } },
+            },
+            "cases": [
+                { "include": "login/*.yml" },
+                {
+                    "id": "LOGIN-1",
+                    "title": "Login",
+                    "children": [{
+                        "title": "Valid password",
+                        "tags": ["smoke"],
+                        "priority": 1,
+                        "parameters": { "browser": ["chrome", "firefox"] },
+                        "steps": [{ "action": "Log in", "expected": "Logged in" }],
+                        "result": { "status": "pass", "date": "2022-08-01" },
+                    }],
+                },
+            ],
+        });
+        assert_eq!(Vec::<String>::new(), errors(spec));
+    }
+
+    #[test]
+    fn invalid() {
+        let spec = json!({
+            "title": "Login",
+            "depth": 0,
+            "config": { "body_bg_color": "white" },
+            "cases": [
+                { "include": "login.yml", "title": "Login" },
+                { "tags": ["smoke"] },
+                { "title": "Case", "unknown": 1 },
+                { "title": "Case", "result": { "status": "passed" } },
+            ],
+        });
+        let errors = errors(spec);
+        for path in [
+            "/depth",
+            "/config",
+            "/cases/0",
+            "/cases/1",
+            "/cases/2",
+            "/cases/3/result",
+        ] {
+            assert!(
+                errors.iter().any(|e| e.starts_with(&format!("{path}: "))),
+                "no error at {path}: {errors:?}"
+            );
+        }
+    }
+}